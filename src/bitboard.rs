@@ -0,0 +1,287 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not};
+
+use crate::PieceColour;
+
+/// a set of squares stored as one bit per board index, so bit 0 is a8 and bit 63 is h1.
+/// iterating gives the indices in the set from lowest to highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SquareSet(pub u64);
+
+impl SquareSet {
+    pub const EMPTY: Self = Self(0);
+
+    pub fn from_index(index: usize) -> Self {
+        Self(1 << index)
+    }
+
+    pub fn contains(self, index: usize) -> bool {
+        self.0 & (1 << index) != 0
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        self.0 |= 1 << index;
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        self.0 &= !(1 << index);
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// number of squares in the set
+    pub fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// lowest index in the set
+    pub fn first(self) -> Option<usize> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.0.trailing_zeros() as usize)
+        }
+    }
+}
+
+impl Iterator for SquareSet {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let index = self.first()?;
+        // clear the lowest bit
+        self.0 &= self.0 - 1;
+        Some(index)
+    }
+}
+
+impl BitAnd for SquareSet {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for SquareSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitXor for SquareSet {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
+impl Not for SquareSet {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl BitAndAssign for SquareSet {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for SquareSet {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// (file, rank) steps. the first 4 are rook directions, the last 4 bishop directions
+const DIRECTIONS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (1, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+];
+
+const KNIGHT_STEPS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+/// the set of squares reached by taking each step once from a square
+const fn steps_from(index: usize, steps: &[(i32, i32)]) -> u64 {
+    let file = (index % 8) as i32;
+    let rank = (index / 8) as i32;
+    let mut set = 0;
+    let mut i = 0;
+    while i < steps.len() {
+        let (new_file, new_rank) = (file + steps[i].0, rank + steps[i].1);
+        if new_file >= 0 && new_file < 8 && new_rank >= 0 && new_rank < 8 {
+            set |= 1 << (new_file + new_rank * 8);
+        }
+        i += 1;
+    }
+    set
+}
+
+/// the squares from a square to the edge of the board in one direction, not including the square
+const fn ray_from(index: usize, (file_step, rank_step): (i32, i32)) -> u64 {
+    let mut file = (index % 8) as i32 + file_step;
+    let mut rank = (index / 8) as i32 + rank_step;
+    let mut set = 0;
+    while file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+        set |= 1 << (file + rank * 8);
+        file += file_step;
+        rank += rank_step;
+    }
+    set
+}
+
+const KNIGHT_ATTACKS: [u64; 64] = {
+    let mut table = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        table[i] = steps_from(i, &KNIGHT_STEPS);
+        i += 1;
+    }
+    table
+};
+
+const KING_ATTACKS: [u64; 64] = {
+    let mut table = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        table[i] = steps_from(i, &DIRECTIONS);
+        i += 1;
+    }
+    table
+};
+
+/// indexed by [colour][square], white pawns attack towards rank 8
+const PAWN_ATTACKS: [[u64; 64]; 2] = {
+    let mut table = [[0; 64]; 2];
+    let mut i = 0;
+    while i < 64 {
+        table[PieceColour::WHITE as usize][i] = steps_from(i, &[(-1, -1), (1, -1)]);
+        table[PieceColour::BLACK as usize][i] = steps_from(i, &[(-1, 1), (1, 1)]);
+        i += 1;
+    }
+    table
+};
+
+/// indexed by [direction][square]
+const RAYS: [[u64; 64]; 8] = {
+    let mut table = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut i = 0;
+        while i < 64 {
+            table[direction][i] = ray_from(i, DIRECTIONS[direction]);
+            i += 1;
+        }
+        direction += 1;
+    }
+    table
+};
+
+/// the squares a slider sees in one direction, up to and including the first occupied square
+fn ray_attacks(direction: usize, index: usize, occupied: SquareSet) -> u64 {
+    let ray = RAYS[direction][index];
+    let blockers = ray & occupied.0;
+    if blockers == 0 {
+        return ray;
+    }
+
+    // the nearest blocker is the lowest bit for directions that increase the index
+    let (file_step, rank_step) = DIRECTIONS[direction];
+    let blocker = if file_step + rank_step * 8 > 0 {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[direction][blocker as usize]
+}
+
+pub fn knight_attacks(index: usize) -> SquareSet {
+    SquareSet(KNIGHT_ATTACKS[index])
+}
+
+pub fn king_attacks(index: usize) -> SquareSet {
+    SquareSet(KING_ATTACKS[index])
+}
+
+/// the squares a pawn of the given colour captures on
+pub fn pawn_attacks(colour: PieceColour, index: usize) -> SquareSet {
+    SquareSet(PAWN_ATTACKS[colour as usize][index])
+}
+
+pub fn rook_attacks(index: usize, occupied: SquareSet) -> SquareSet {
+    SquareSet((0..4).fold(0, |set, direction| {
+        set | ray_attacks(direction, index, occupied)
+    }))
+}
+
+pub fn bishop_attacks(index: usize, occupied: SquareSet) -> SquareSet {
+    SquareSet((4..8).fold(0, |set, direction| {
+        set | ray_attacks(direction, index, occupied)
+    }))
+}
+
+pub fn queen_attacks(index: usize, occupied: SquareSet) -> SquareSet {
+    rook_attacks(index, occupied) | bishop_attacks(index, occupied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::coordinate_to_index;
+    #[test]
+    fn iterate() {
+        let set = SquareSet::from_index(3) | SquareSet::from_index(40) | SquareSet::from_index(63);
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.collect::<Vec<_>>(), vec![3, 40, 63])
+    }
+    #[test]
+    fn leaper_attacks() {
+        // a knight in the corner and a king on the edge
+        assert_eq!(knight_attacks(coordinate_to_index(0, 0)).len(), 2);
+        assert_eq!(king_attacks(coordinate_to_index(4, 7)).len(), 5);
+        assert_eq!(
+            pawn_attacks(PieceColour::WHITE, coordinate_to_index(0, 6)),
+            SquareSet::from_index(coordinate_to_index(1, 5))
+        )
+    }
+    #[test]
+    fn slider_attacks() {
+        // rook on d4 blocked on d6 and f4
+        let rook = coordinate_to_index(3, 4);
+        let occupied = SquareSet::from_index(coordinate_to_index(3, 2))
+            | SquareSet::from_index(coordinate_to_index(5, 4));
+        let attacks = rook_attacks(rook, occupied);
+        assert_eq!(attacks.len(), 2 + 2 + 3 + 3);
+        assert!(attacks.contains(coordinate_to_index(3, 2)));
+        assert!(!attacks.contains(coordinate_to_index(3, 1)));
+        assert!(!attacks.contains(coordinate_to_index(6, 4)));
+
+        // bishop on a1 sees the whole long diagonal on an empty board
+        assert_eq!(
+            bishop_attacks(coordinate_to_index(0, 7), SquareSet::EMPTY).len(),
+            7
+        )
+    }
+}
@@ -1,11 +1,18 @@
-use crate::io::Command;
-use crate::piece::{Bishop, King, Knight, Pawn, Piece, Queen, Rook};
-use crate::{PieceColour, PieceType, UnitResult};
+use crate::bitboard::{self, SquareSet};
+use crate::fen::FenError;
+use crate::game::GameStatus;
+use crate::material::Material;
+use crate::moves::Move;
+use crate::piece::ColouredPiece;
+use crate::zobrist;
+use crate::{PieceColour, PieceType};
+
+use std::fmt::Display;
+use std::str::FromStr;
 
-use arr_macro::arr;
 use regex::Regex;
 
-pub type Square = Option<Box<dyn Piece>>;
+pub type Square = Option<ColouredPiece>;
 
 /// converts a (file, rank) pair to an index
 pub fn coordinate_to_index(file: u8, rank: u8) -> usize {
@@ -17,168 +24,1172 @@ pub fn index_to_coordinate(index: usize) -> (u8, u8) {
     ((index % 8) as u8, (index / 8) as u8)
 }
 
+/// converts an index to the name of the square, eg. "e4"
+pub fn index_to_name(index: usize) -> String {
+    let (file, rank) = index_to_coordinate(index);
+    format!("{}{}", (b'a' + file) as char, 8 - rank)
+}
+
+/// gets the fen letter for a piece, uppercase for white
+pub fn piece_to_char(colour: PieceColour, piece_type: PieceType) -> char {
+    let chr = match piece_type {
+        PieceType::KING => 'k',
+        PieceType::QUEEN => 'q',
+        PieceType::ROOK => 'r',
+        PieceType::BISHOP => 'b',
+        PieceType::KNIGHT => 'n',
+        PieceType::PAWN => 'p',
+    };
+    match colour {
+        PieceColour::WHITE => chr.to_ascii_uppercase(),
+        PieceColour::BLACK => chr,
+    }
+}
+
+/// which sides each colour is still allowed to castle on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl CastlingRights {
+    pub fn none() -> Self {
+        Self {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+        }
+    }
+
+    /// parses the castling field of a fen string, eg. "KQkq" or "-".
+    /// column is where the field starts in the fen string
+    pub fn from_fen(field: &str, column: usize) -> Result<Self, FenError> {
+        let mut rights = Self::none();
+        if field == "-" {
+            return Ok(rights);
+        }
+
+        for (i, chr) in field.char_indices() {
+            let right = match chr {
+                'K' => &mut rights.white_king_side,
+                'Q' => &mut rights.white_queen_side,
+                'k' => &mut rights.black_king_side,
+                'q' => &mut rights.black_queen_side,
+                _ => return Err(FenError::INVALID_CASTLING { column: column + i }),
+            };
+            // each right can only be given once
+            if *right {
+                return Err(FenError::INVALID_CASTLING { column: column + i });
+            }
+            *right = true;
+        }
+
+        Ok(rights)
+    }
+
+    /// writes the castling field of a fen string
+    pub fn to_fen(self) -> String {
+        let mut field = String::new();
+        if self.white_king_side {
+            field.push('K');
+        }
+        if self.white_queen_side {
+            field.push('Q');
+        }
+        if self.black_king_side {
+            field.push('k');
+        }
+        if self.black_queen_side {
+            field.push('q');
+        }
+        if field.is_empty() {
+            field.push('-');
+        }
+        field
+    }
+
+    pub fn king_side(&self, colour: PieceColour) -> bool {
+        match colour {
+            PieceColour::WHITE => self.white_king_side,
+            PieceColour::BLACK => self.black_king_side,
+        }
+    }
+
+    pub fn queen_side(&self, colour: PieceColour) -> bool {
+        match colour {
+            PieceColour::WHITE => self.white_queen_side,
+            PieceColour::BLACK => self.black_queen_side,
+        }
+    }
+
+    /// removes both rights for a colour, eg. when its king moves
+    pub fn revoke(&mut self, colour: PieceColour) {
+        match colour {
+            PieceColour::WHITE => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            }
+            PieceColour::BLACK => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            }
+        }
+    }
+
+    /// removes the right tied to a rook's starting square, eg. when something moves from or to it
+    pub fn revoke_square(&mut self, file: u8, rank: u8) {
+        match (file, rank) {
+            (7, 7) => self.white_king_side = false,
+            (0, 7) => self.white_queen_side = false,
+            (7, 0) => self.black_king_side = false,
+            (0, 0) => self.black_queen_side = false,
+            _ => (),
+        }
+    }
+}
+
+/// what a move destroyed, so that it can be taken back
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoInfo {
+    pub captured: Square,
+    pub castling_rights: CastlingRights,
+    pub en_passant_ind: Option<usize>,
+    pub halfmove_clock: u32,
+    pub hash: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Board {
+    /// only changed through make and unmake, which keep the square sets below in sync
     pub squares: [Square; 64],
     pub turn: PieceColour,
+    pub castling_rights: CastlingRights,
+    /// the square a pawn skipped over with a double push, only kept while a pawn
+    /// of the side to move could capture on it, so it is hashed only when it matters
     pub en_passant_ind: Option<usize>,
+    /// halfmoves since the last capture or pawn move
+    pub halfmove_clock: u32,
+    /// starts at 1 and increments after black's move
+    pub fullmove_number: u32,
+    /// zobrist hash of the position, kept up to date by moves
+    pub hash: u64,
+    /// squares of each piece type, indexed by PieceType. kept in sync with squares
+    piece_sets: [SquareSet; 6],
+    /// squares of each colour's pieces, indexed by PieceColour
+    colour_sets: [SquareSet; 2],
 }
 
 impl Board {
-    pub fn from_fen(fen: &str) -> Result<Self, String> {
-        let mut f: Vec<_> = fen
-            .chars()
-            .rev()
-            .collect::<String>()
-            .split(" ")
-            .map(|s| s.to_owned())
-            .collect();
+    /// a board with no pieces and white to move
+    pub fn empty() -> Self {
+        let mut board = Self {
+            squares: [None; 64],
+            turn: PieceColour::WHITE,
+            castling_rights: CastlingRights::none(),
+            en_passant_ind: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: 0,
+            piece_sets: [SquareSet::EMPTY; 6],
+            colour_sets: [SquareSet::EMPTY; 2],
+        };
+        board.hash = board.compute_hash();
+        board
+    }
+
+    /// parses a fen string. the clocks may be left off, in which case they default to "0 1"
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        // (column, field) pairs
+        let mut fields = fen
+            .split_whitespace()
+            .map(|field| (field.as_ptr() as usize - fen.as_ptr() as usize, field));
 
         // position
-        let mut squares: [Square; 64] = arr![None; 64];
-        let mut ind: usize = 0;
-        let mut pos = f.pop().ok_or("Invalid fen: No position")?;
-
-        loop {
-            match pos.pop() {
-                Some(chr) => {
-                    if chr.is_numeric() {
-                        ind += chr.to_digit(10).unwrap() as usize;
-                    } else if chr.is_alphabetic() {
-                        let is_white = !chr.is_lowercase();
-                        let (file, rank) = index_to_coordinate(ind);
-                        squares[ind] = match chr.to_lowercase().to_string().as_str() {
-                            "p" => Some(Box::new(Pawn::new(is_white, file, rank))),
-                            "n" => Some(Box::new(Knight::new(is_white, file, rank))),
-                            "b" => Some(Box::new(Bishop::new(is_white, file, rank))),
-                            "r" => Some(Box::new(Rook::new(is_white, file, rank))),
-                            "q" => Some(Box::new(Queen::new(is_white, file, rank))),
-                            "k" => Some(Box::new(King::new(is_white, file, rank))),
-                            _ => None,
-                            // _ => {
-                            //     return Err(format!(
-                            //         "Invalid fen: cannot parse char '{}' as a piece",
-                            //         chr
-                            //     ))
-                            // }
-                        };
-                        ind += 1;
+        let mut squares: [Square; 64] = [None; 64];
+        let (pos_column, pos) = fields.next().ok_or(FenError::MISSING_FIELD {
+            field: "position",
+            column: fen.len(),
+        })?;
+        let mut king_counts = [0; 2];
+        let mut file: u8 = 0;
+        let mut rank: u8 = 0;
+
+        for (i, chr) in pos.char_indices() {
+            let column = pos_column + i;
+
+            if chr == '/' {
+                if file < 8 {
+                    return Err(FenError::RANK_UNDERFLOW { column });
+                }
+                if rank == 7 {
+                    return Err(FenError::WRONG_RANK_COUNT { column });
+                }
+                file = 0;
+                rank += 1;
+                continue;
+            }
+
+            if let Some(empty) = chr.to_digit(10).filter(|&digit| digit != 0) {
+                file += empty as u8;
+                if file > 8 {
+                    return Err(FenError::RANK_OVERFLOW { column });
+                }
+                continue;
+            }
+
+            if file == 8 {
+                return Err(FenError::RANK_OVERFLOW { column });
+            }
+            let colour = if chr.is_ascii_uppercase() {
+                PieceColour::WHITE
+            } else {
+                PieceColour::BLACK
+            };
+            let piece_type = match chr.to_ascii_lowercase() {
+                'p' => {
+                    if rank == 0 || rank == 7 {
+                        return Err(FenError::PAWN_ON_BACK_RANK { column });
+                    }
+                    PieceType::PAWN
+                }
+                'n' => PieceType::KNIGHT,
+                'b' => PieceType::BISHOP,
+                'r' => PieceType::ROOK,
+                'q' => PieceType::QUEEN,
+                'k' => {
+                    king_counts[colour as usize] += 1;
+                    if king_counts[colour as usize] > 1 {
+                        return Err(FenError::EXTRA_KING { colour, column });
                     }
+                    PieceType::KING
                 }
-                None => break,
+                _ => return Err(FenError::INVALID_PIECE { chr, column }),
             };
+            squares[coordinate_to_index(file, rank)] = Some(ColouredPiece::new(colour, piece_type));
+            file += 1;
+        }
+
+        let end_column = pos_column + pos.len();
+        if rank != 7 {
+            return Err(FenError::WRONG_RANK_COUNT { column: end_column });
+        }
+        if file < 8 {
+            return Err(FenError::RANK_UNDERFLOW { column: end_column });
+        }
+        for colour in PieceColour::COLOURS {
+            if king_counts[colour as usize] == 0 {
+                return Err(FenError::MISSING_KING {
+                    colour,
+                    column: pos_column,
+                });
+            }
         }
         // end position
 
         // turn
-        let t = f.pop().ok_or("Invalid fen: No turn")?;
-        let turn = match t.as_str() {
+        let (turn_column, t) = fields.next().ok_or(FenError::MISSING_FIELD {
+            field: "turn",
+            column: fen.len(),
+        })?;
+        let turn = match t {
             "w" => PieceColour::WHITE,
             "b" => PieceColour::BLACK,
-            _ => return Err(format!("Invalid fen: '{}' is not a valid turn", t)),
+            _ => {
+                return Err(FenError::INVALID_TURN {
+                    column: turn_column,
+                })
+            }
         };
         // end turn
 
-        // TODO: castling
-        f.pop();
+        // castling
+        let (castling_column, c) = fields.next().ok_or(FenError::MISSING_FIELD {
+            field: "castling rights",
+            column: fen.len(),
+        })?;
+        let castling_rights = CastlingRights::from_fen(c, castling_column)?;
+        // end castling
 
         // en passant
-        let re = Regex::new(r"(?<rank>[1-8])(?<file>[a-h])").unwrap();
-        let en_passant_ind = match re.captures(f.pop().unwrap().as_str()) {
-            None => None,
-            Some(thing) => {
-                let file = thing
-                    .name("file")
-                    .unwrap()
-                    .as_str()
-                    .chars()
-                    .collect::<Vec<char>>()[0] as u8
-                    - 'a' as u8;
-                let rank = thing
-                    .name("rank")
-                    .unwrap()
-                    .as_str()
-                    .chars()
-                    .collect::<Vec<char>>()[0] as u8
-                    - '1' as u8;
-                Some(coordinate_to_index(file, rank))
+        let (en_passant_column, e) = fields.next().ok_or(FenError::MISSING_FIELD {
+            field: "en passant square",
+            column: fen.len(),
+        })?;
+        let en_passant_ind = if e == "-" {
+            None
+        } else {
+            let invalid = FenError::INVALID_EN_PASSANT {
+                column: en_passant_column,
+            };
+            let re = Regex::new(r"^(?<file>[a-h])(?<rank>[36])$").unwrap();
+            let captures = re.captures(e).ok_or(invalid)?;
+            let file = captures["file"].as_bytes()[0] - b'a';
+            let rank = b'8' - captures["rank"].as_bytes()[0];
+
+            // the square is behind the opponent's pawn, on the sixth rank when white
+            // is to move and the third when black is
+            let (pushed_colour, direction, square_rank): (PieceColour, i32, &str) = match turn {
+                PieceColour::WHITE => (PieceColour::BLACK, 1, "6"),
+                PieceColour::BLACK => (PieceColour::WHITE, -1, "3"),
+            };
+            if &captures["rank"] != square_rank {
+                return Err(invalid);
             }
+
+            // the pawn that just double pushed must be in front of the square,
+            // with the square and the one behind it empty
+            let pawn_rank = (rank as i32 + direction) as u8;
+            let start_rank = (rank as i32 - direction) as u8;
+            let has_pawn = match &squares[coordinate_to_index(file, pawn_rank)] {
+                Some(piece) => {
+                    piece.colour() == pushed_colour && piece.piece_type() == PieceType::PAWN
+                }
+                None => false,
+            };
+            if !has_pawn
+                || squares[coordinate_to_index(file, rank)].is_some()
+                || squares[coordinate_to_index(file, start_rank)].is_some()
+            {
+                return Err(invalid);
+            }
+
+            Some(coordinate_to_index(file, rank))
+        };
+        // end en passant
+
+        // halfmove clock
+        let halfmove_clock = match fields.next() {
+            Some((column, h)) => h
+                .parse::<u32>()
+                .map_err(|_| FenError::INVALID_CLOCK { column })?,
+            None => 0,
         };
+        // end halfmove clock
 
-        // TODO: halfmove clock (50 move rule)
+        // fullmove number
+        let fullmove_number = match fields.next() {
+            Some((column, n)) => match n.parse::<u32>() {
+                Ok(n) if n >= 1 => n,
+                _ => return Err(FenError::INVALID_CLOCK { column }),
+            },
+            None => 1,
+        };
+        // end fullmove number
 
-        // TODO: fullmove clock
+        if let Some((column, _)) = fields.next() {
+            return Err(FenError::TOO_MANY_FIELDS { column });
+        }
 
-        Ok(Self {
-            squares,
+        let mut board = Self {
             turn,
-            en_passant_ind,
-        })
+            castling_rights,
+            halfmove_clock,
+            fullmove_number,
+            ..Self::empty()
+        };
+        for (ind, square) in squares.into_iter().enumerate() {
+            if let Some(piece) = square {
+                board.put_piece(ind, piece);
+            }
+        }
+        // a square no pawn can capture on doesn't change the position
+        board.en_passant_ind = en_passant_ind.filter(|&ind| board.pawn_attacks_square(turn, ind));
+        board.hash = board.compute_hash();
+
+        Ok(board)
     }
 
-    pub fn update(&mut self, command: Command) -> UnitResult {
-        match command {
-            Command::Move {
-                start_file,
-                start_rank,
-                end_file,
-                end_rank,
-                promotion_piece,
-            } => self.make_move(start_file, start_rank, end_file, end_rank, promotion_piece),
-            _ => (),
-        }
-        Ok(())
-    }
-
-    fn make_move(
-        &mut self,
-        start_file: u8,
-        start_rank: u8,
-        end_file: u8,
-        end_rank: u8,
-        promotion_piece: Option<PieceType>,
-    ) {
-        if let Some(mut piece) = self.squares[coordinate_to_index(start_file, start_rank)].take() {
-            if piece.is_pseudo_legal(end_file, end_rank, self) {
-                //  promotion
-                if piece.piece_type() == PieceType::PAWN && (end_rank == 0 || end_rank == 7) {
-                    let is_white = piece.colour() == PieceColour::WHITE;
-                    piece = match promotion_piece.unwrap() {
-                        PieceType::QUEEN => Box::new(Queen::new(is_white, end_file, end_rank)),
-                        PieceType::ROOK => Box::new(Rook::new(is_white, end_file, end_rank)),
-                        PieceType::BISHOP => Box::new(Bishop::new(is_white, end_file, end_rank)),
-                        PieceType::KNIGHT => Box::new(Knight::new(is_white, end_file, end_rank)),
-                        _ => panic!("Invalid promotion piece"),
-                    };
-                } else {
-                    piece.update_pos(end_file, end_rank);
-                }
+    /// writes the position as a fen string
+    pub fn to_fen(self) -> String {
+        let mut fen = String::new();
 
-                // en passant capture
-                if let Some(ind) = self.en_passant_ind {
-                    if piece.piece_type() == PieceType::PAWN
-                        && coordinate_to_index(end_file, end_rank) == ind
-                    {
-                        self.squares[coordinate_to_index(end_file, start_rank)] = None;
+        // position
+        for rank in 0..8 {
+            let mut empty = 0;
+            for file in 0..8 {
+                match &self.squares[coordinate_to_index(file, rank)] {
+                    Some(piece) => {
+                        if empty != 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece_to_char(piece.colour(), piece.piece_type()));
                     }
+                    None => empty += 1,
                 }
+            }
+            if empty != 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank != 7 {
+                fen.push('/');
+            }
+        }
 
-                // en passant square
-                if piece.piece_type() == PieceType::PAWN && start_rank.abs_diff(end_rank) == 2 {
-                    self.en_passant_ind =
-                        Some(coordinate_to_index(end_file, (start_rank + end_rank) / 2));
-                } else {
-                    self.en_passant_ind = None;
-                }
+        // turn
+        fen.push_str(match self.turn {
+            PieceColour::WHITE => " w ",
+            PieceColour::BLACK => " b ",
+        });
+
+        // castling
+        fen.push_str(&self.castling_rights.to_fen());
+
+        // en passant
+        match self.en_passant_ind {
+            Some(ind) => fen.push_str(&format!(" {} ", index_to_name(ind))),
+            None => fen.push_str(" - "),
+        }
+
+        // clocks
+        fen.push_str(&format!("{} {}", self.halfmove_clock, self.fullmove_number));
+
+        fen
+    }
+
+    /// calculates the zobrist hash of the position from scratch
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+
+        for (ind, square) in self.squares.iter().enumerate() {
+            if let Some(piece) = square {
+                hash ^= zobrist::piece_key(piece.colour(), piece.piece_type(), ind);
+            }
+        }
+        if self.turn == PieceColour::BLACK {
+            hash ^= zobrist::side_key();
+        }
+        hash ^= zobrist::castling_key(self.castling_rights);
+        if let Some(ind) = self.en_passant_ind {
+            hash ^= zobrist::en_passant_key(ind);
+        }
+
+        hash
+    }
+
+    /// gets the squares holding pieces of a colour and type
+    pub fn pieces(&self, colour: PieceColour, piece_type: PieceType) -> SquareSet {
+        self.piece_sets[piece_type as usize] & self.colour_sets[colour as usize]
+    }
+
+    /// gets the squares holding any of a colour's pieces
+    pub fn colour_pieces(&self, colour: PieceColour) -> SquareSet {
+        self.colour_sets[colour as usize]
+    }
+
+    /// gets the squares holding any piece
+    pub fn occupied(&self) -> SquareSet {
+        self.colour_sets[0] | self.colour_sets[1]
+    }
+
+    /// puts a piece on an empty square
+    fn put_piece(&mut self, ind: usize, piece: ColouredPiece) {
+        self.piece_sets[piece.piece_type() as usize].insert(ind);
+        self.colour_sets[piece.colour() as usize].insert(ind);
+        self.squares[ind] = Some(piece);
+    }
+
+    /// removes the piece on a square, if there is one
+    fn take_piece(&mut self, ind: usize) -> Square {
+        let piece = self.squares[ind].take()?;
+        self.piece_sets[piece.piece_type() as usize].remove(ind);
+        self.colour_sets[piece.colour() as usize].remove(ind);
+        Some(piece)
+    }
+
+    /// gets every move for the side to move, including ones that leave its king in check
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for start_ind in self.colour_pieces(self.turn) {
+            if let Some(piece) = self.squares[start_ind] {
+                let (file, rank) = index_to_coordinate(start_ind);
+                piece.pseudo_legal_moves(file, rank, self, &mut moves);
+            }
+        }
+        moves
+    }
+
+    /// gets every legal move for the side to move
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = self.pseudo_legal_moves();
+        // scratch board to try moves on
+        let mut board = *self;
+        moves.retain(|&mv| board.keeps_king_safe(mv));
+        moves
+    }
+
+    /// checks if a move from pseudo_legal_moves is legal. the move is tried on the board,
+    /// which is left as it was
+    pub fn keeps_king_safe(&mut self, mv: Move) -> bool {
+        if mv.is_castle() {
+            // castling cannot be done out of or through check
+            let (start_file, rank) = mv.start();
+            let (end_file, _) = mv.end();
+            let attacked = self.attacked_squares(!self.turn);
+            if attacked.contains(mv.from())
+                || attacked.contains(coordinate_to_index((start_file + end_file) / 2, rank))
+            {
+                return false;
+            }
+        }
+        !self.leaves_king_in_check(mv)
+    }
 
-                self.turn = !self.turn;
-                self.squares[coordinate_to_index(end_file, end_rank)] = Some(piece);
-                println!("{:?}'s turn", self.turn);
+    /// checks if a move with flags is legal, without generating every move.
+    /// useful for moves remembered from other positions, eg. in the transposition table
+    pub fn is_legal(&self, mv: Move) -> bool {
+        let piece = match self.squares[mv.from()] {
+            Some(piece) if piece.colour() == self.turn => piece,
+            _ => return false,
+        };
+        let (file, rank) = mv.start();
+        let mut moves = Vec::new();
+        piece.pseudo_legal_moves(file, rank, self, &mut moves);
+        let mut board = *self;
+        moves.contains(&mv) && board.keeps_king_safe(mv)
+    }
+
+    /// gets every square attacked by the given colour's pieces
+    pub fn attacked_squares(&self, by: PieceColour) -> SquareSet {
+        let mut attacked = SquareSet::EMPTY;
+        for ind in self.colour_pieces(by) {
+            if let Some(piece) = self.squares[ind] {
+                let (file, rank) = index_to_coordinate(ind);
+                attacked |= piece.attacks(file, rank, self);
+            }
+        }
+        attacked
+    }
+
+    /// checks if making a pseudo-legal move would leave the mover's king in check
+    fn leaves_king_in_check(&mut self, mv: Move) -> bool {
+        let undo = self.make(mv);
+        let in_check = self.is_in_check(!self.turn);
+        self.unmake(mv, undo);
+        in_check
+    }
+
+    /// checks if a square is attacked by any piece of the given colour
+    pub fn is_square_attacked(&self, file: u8, rank: u8, by: PieceColour) -> bool {
+        let ind = coordinate_to_index(file, rank);
+        let occupied = self.occupied();
+        let queens = self.pieces(by, PieceType::QUEEN);
+
+        // a piece attacks the square if it stands where that piece would attack from the square
+        let attackers = (bitboard::pawn_attacks(!by, ind) & self.pieces(by, PieceType::PAWN))
+            | (bitboard::knight_attacks(ind) & self.pieces(by, PieceType::KNIGHT))
+            | (bitboard::king_attacks(ind) & self.pieces(by, PieceType::KING))
+            | (bitboard::rook_attacks(ind, occupied) & (self.pieces(by, PieceType::ROOK) | queens))
+            | (bitboard::bishop_attacks(ind, occupied)
+                & (self.pieces(by, PieceType::BISHOP) | queens));
+
+        !attackers.is_empty()
+    }
+
+    /// gets the pieces of both colours that attack a square, as if only the squares in
+    /// occupied had pieces on them. pieces missing from occupied are not included
+    pub fn attackers_to(&self, ind: usize, occupied: SquareSet) -> SquareSet {
+        let pieces = |piece_type: PieceType| self.piece_sets[piece_type as usize];
+        let queens = pieces(PieceType::QUEEN);
+
+        let attackers = (bitboard::pawn_attacks(PieceColour::BLACK, ind)
+            & self.pieces(PieceColour::WHITE, PieceType::PAWN))
+            | (bitboard::pawn_attacks(PieceColour::WHITE, ind)
+                & self.pieces(PieceColour::BLACK, PieceType::PAWN))
+            | (bitboard::knight_attacks(ind) & pieces(PieceType::KNIGHT))
+            | (bitboard::king_attacks(ind) & pieces(PieceType::KING))
+            | (bitboard::rook_attacks(ind, occupied) & (pieces(PieceType::ROOK) | queens))
+            | (bitboard::bishop_attacks(ind, occupied) & (pieces(PieceType::BISHOP) | queens));
+
+        attackers & occupied
+    }
+
+    /// checks if a pawn of the given colour attacks a square, eg. to capture en passant on it
+    fn pawn_attacks_square(&self, colour: PieceColour, ind: usize) -> bool {
+        !(bitboard::pawn_attacks(!colour, ind) & self.pieces(colour, PieceType::PAWN)).is_empty()
+    }
+
+    /// finds the (file, rank) of the given colour's king
+    pub fn king_square(&self, colour: PieceColour) -> Option<(u8, u8)> {
+        self.pieces(colour, PieceType::KING)
+            .first()
+            .map(index_to_coordinate)
+    }
+
+    /// checks if the given colour's king is attacked
+    pub fn is_in_check(&self, colour: PieceColour) -> bool {
+        match self.king_square(colour) {
+            Some((file, rank)) => self.is_square_attacked(file, rank, !colour),
+            None => false,
+        }
+    }
+
+    /// counts the pieces on the board
+    pub fn material(&self) -> Material {
+        let mut material = Material::default();
+        for (ind, square) in self.squares.iter().enumerate() {
+            if let Some(piece) = square {
+                let (file, rank) = index_to_coordinate(ind);
+                material.add(piece.colour(), piece.piece_type(), file, rank);
+            }
+        }
+        material
+    }
+
+    /// gets the status of the game with the current side to move.
+    /// draws that have to be claimed are not included
+    pub fn status(&self) -> GameStatus {
+        if !self.legal_moves().is_empty() {
+            if self.halfmove_clock >= 150 {
+                GameStatus::SEVENTY_FIVE_MOVE_RULE
+            } else if self.material().is_insufficient() {
+                GameStatus::INSUFFICIENT_MATERIAL
             } else {
-                println!("Cannot make move: invalid move");
-                self.squares[coordinate_to_index(start_file, start_rank)] = Some(piece);
+                GameStatus::ONGOING
             }
+        } else if self.is_in_check(self.turn) {
+            GameStatus::CHECKMATE(!self.turn)
+        } else {
+            GameStatus::STALEMATE
+        }
+    }
+
+    /// checks if either side may claim a draw under the fifty move rule
+    pub fn can_claim_fifty_move_rule(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    /// finds the legal move with the same squares as a move entered by a player.
+    /// the promotion piece is ignored for moves that are not promotions
+    pub fn find_move(&self, mv: Move) -> Option<Move> {
+        self.legal_moves().into_iter().find(|legal| {
+            legal.from() == mv.from()
+                && legal.to() == mv.to()
+                && (!legal.is_promotion() || legal.promotion() == mv.promotion())
+        })
+    }
+
+    /// checks that a move is legal and makes it
+    pub fn make_move(&mut self, mv: Move) -> Result<(Move, UndoInfo), String> {
+        if self.squares[mv.from()].is_none() {
+            return Err("Cannot make move: piece doesn't exist".to_owned());
+        }
+
+        let mv = self.find_move(mv).ok_or("Cannot make move: invalid move")?;
+        let undo = self.make(mv);
+
+        Ok((mv, undo))
+    }
+
+    /// makes a move from legal_moves without checking that it is legal.
+    /// the returned info can be given to unmake to take the move back
+    pub fn make(&mut self, mv: Move) -> UndoInfo {
+        let (start_ind, end_ind) = (mv.from(), mv.to());
+        let (start_file, start_rank) = mv.start();
+        let (end_file, end_rank) = mv.end();
+
+        let mut undo = UndoInfo {
+            captured: None,
+            castling_rights: self.castling_rights,
+            en_passant_ind: self.en_passant_ind,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+        };
+
+        let mut piece = match self.take_piece(start_ind) {
+            Some(piece) => piece,
+            None => return undo,
+        };
+        self.hash ^= zobrist::piece_key(piece.colour(), piece.piece_type(), start_ind);
+
+        // clocks
+        if piece.piece_type() == PieceType::PAWN || mv.is_capture() {
+            self.halfmove_clock = 0;
         } else {
-            println!("Cannot make move: piece doesn't exist");
+            self.halfmove_clock += 1;
+        }
+        if piece.colour() == PieceColour::BLACK {
+            self.fullmove_number += 1;
         }
+
+        // capture, which is beside the end square for en passant
+        if mv.is_capture() {
+            let captured_ind = if mv.is_en_passant() {
+                coordinate_to_index(end_file, start_rank)
+            } else {
+                end_ind
+            };
+            if let Some(captured) = self.take_piece(captured_ind) {
+                self.hash ^=
+                    zobrist::piece_key(captured.colour(), captured.piece_type(), captured_ind);
+                undo.captured = Some(captured);
+            }
+        }
+
+        //  promotion
+        if let Some(promotion_piece) = mv.promotion() {
+            piece = ColouredPiece::new(piece.colour(), promotion_piece);
+        }
+
+        // castling
+        if mv.is_castle() {
+            let (rook_start_ind, rook_end_ind) = castling_rook_squares(start_rank, end_file);
+            if let Some(rook) = self.take_piece(rook_start_ind) {
+                self.hash ^= zobrist::piece_key(rook.colour(), PieceType::ROOK, rook_start_ind);
+                self.hash ^= zobrist::piece_key(rook.colour(), PieceType::ROOK, rook_end_ind);
+                self.put_piece(rook_end_ind, rook);
+            }
+        }
+
+        // castling rights
+        self.hash ^= zobrist::castling_key(self.castling_rights);
+        if piece.piece_type() == PieceType::KING {
+            self.castling_rights.revoke(piece.colour());
+        }
+        self.castling_rights.revoke_square(start_file, start_rank);
+        self.castling_rights.revoke_square(end_file, end_rank);
+        self.hash ^= zobrist::castling_key(self.castling_rights);
+
+        // en passant square
+        if let Some(ind) = self.en_passant_ind {
+            self.hash ^= zobrist::en_passant_key(ind);
+        }
+        self.en_passant_ind = None;
+        if mv.is_double_push() {
+            let ind = coordinate_to_index(end_file, (start_rank + end_rank) / 2);
+            if self.pawn_attacks_square(!piece.colour(), ind) {
+                self.en_passant_ind = Some(ind);
+                self.hash ^= zobrist::en_passant_key(ind);
+            }
+        }
+
+        self.turn = !self.turn;
+        self.hash ^= zobrist::side_key();
+        self.hash ^= zobrist::piece_key(piece.colour(), piece.piece_type(), end_ind);
+        self.put_piece(end_ind, piece);
+
+        undo
+    }
+
+    /// takes back a move made by make, restoring the board exactly
+    pub fn unmake(&mut self, mv: Move, undo: UndoInfo) {
+        let (start_ind, end_ind) = (mv.from(), mv.to());
+        let (start_file, start_rank) = mv.start();
+        let (end_file, _) = mv.end();
+
+        let mut piece = match self.take_piece(end_ind) {
+            Some(piece) => piece,
+            None => return,
+        };
+
+        // promotion
+        if mv.is_promotion() {
+            piece = ColouredPiece::new(piece.colour(), PieceType::PAWN);
+        }
+
+        // castling
+        if mv.is_castle() {
+            let (rook_start_ind, rook_end_ind) = castling_rook_squares(start_rank, end_file);
+            if let Some(rook) = self.take_piece(rook_end_ind) {
+                self.put_piece(rook_start_ind, rook);
+            }
+        }
+
+        // capture, which is beside the end square for en passant
+        if let Some(captured) = undo.captured {
+            let captured_ind = if mv.is_en_passant() {
+                coordinate_to_index(end_file, start_rank)
+            } else {
+                end_ind
+            };
+            self.put_piece(captured_ind, captured);
+        }
+
+        if piece.colour() == PieceColour::BLACK {
+            self.fullmove_number -= 1;
+        }
+        self.put_piece(start_ind, piece);
+        self.turn = !self.turn;
+        self.castling_rights = undo.castling_rights;
+        self.en_passant_ind = undo.en_passant_ind;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.hash = undo.hash;
+    }
+}
+
+/// gets the (start, end) indexes of the rook when the king castles to end_file
+fn castling_rook_squares(rank: u8, end_file: u8) -> (usize, usize) {
+    if end_file == 6 {
+        (coordinate_to_index(7, rank), coordinate_to_index(5, rank))
+    } else {
+        (coordinate_to_index(0, rank), coordinate_to_index(3, rank))
+    }
+}
+
+impl Display for Board {
+    /// formats the board as a fen string
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_fen())
+    }
+}
+
+impl FromStr for Board {
+    type Err = FenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_fen(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_FEN;
+
+    fn mv(start_file: u8, start_rank: u8, end_file: u8, end_rank: u8) -> Move {
+        Move::from_squares(
+            coordinate_to_index(start_file, start_rank),
+            coordinate_to_index(end_file, end_rank),
+            None,
+        )
+    }
+    #[test]
+    fn starting_position_moves() {
+        let b = Board::from_fen(STARTING_FEN).unwrap();
+        assert_eq!(b.legal_moves().len(), 20)
+    }
+    #[test]
+    fn pinned_piece_cannot_move() {
+        // white knight on e2 is pinned by the rook on e8
+        let b = Board::from_fen("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert!(b.legal_moves().iter().all(|mv| mv.start() != (4, 6)))
+    }
+    #[test]
+    fn single_move_legality() {
+        let b = Board::from_fen("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        for legal in b.legal_moves() {
+            assert!(b.is_legal(legal))
+        }
+        let pseudo_legal = b.pseudo_legal_moves();
+        assert!(pseudo_legal.len() > b.legal_moves().len());
+        // the pinned knight, a move without the flags it needs and a move for the wrong side
+        let knight_move = pseudo_legal.iter().find(|mv| mv.start() == (4, 6)).unwrap();
+        assert!(!b.is_legal(*knight_move));
+        assert!(b.is_legal(Move::new(60, 59, None, Move::QUIET)));
+        assert!(!b.is_legal(Move::new(60, 59, None, Move::CAPTURE)));
+        assert!(!b.is_legal(mv(4, 0, 3, 0)))
+    }
+    #[test]
+    fn promotion_moves() {
+        let b = Board::from_fen("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let promotions = b
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.is_promotion())
+            .count();
+        assert_eq!(promotions, 4)
+    }
+    #[test]
+    fn pawn_attacks_empty_square() {
+        let b = Board::from_fen("4k3/8/8/8/8/3p4/8/4K3 w - - 0 1").unwrap();
+        assert!(b.is_square_attacked(4, 6, PieceColour::BLACK));
+        assert!(!b.is_square_attacked(3, 6, PieceColour::BLACK))
+    }
+    #[test]
+    fn checkmate() {
+        // fool's mate
+        let b = Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+            .unwrap();
+        assert!(b.is_in_check(PieceColour::WHITE));
+        assert_eq!(b.status(), GameStatus::CHECKMATE(PieceColour::BLACK))
+    }
+    #[test]
+    fn stalemate() {
+        let b = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(!b.is_in_check(PieceColour::BLACK));
+        assert_eq!(b.status(), GameStatus::STALEMATE)
+    }
+    #[test]
+    fn castling() {
+        let mut b = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert!(b.find_move(mv(4, 7, 6, 7)).is_some());
+        assert!(b.find_move(mv(4, 7, 2, 7)).is_some());
+
+        b.make_move(mv(4, 7, 2, 7)).unwrap();
+        assert_eq!(
+            b.squares[coordinate_to_index(3, 7)]
+                .as_ref()
+                .unwrap()
+                .piece_type(),
+            PieceType::ROOK
+        );
+        assert!(b.squares[coordinate_to_index(0, 7)].is_none());
+        assert!(!b.castling_rights.king_side(PieceColour::WHITE));
+        assert!(!b.castling_rights.queen_side(PieceColour::WHITE));
+    }
+    #[test]
+    fn castling_through_check() {
+        // the rook on f8 covers f1, so white can only castle queen side
+        let b = Board::from_fen("k4r2/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert!(b.find_move(mv(4, 7, 6, 7)).is_none());
+        assert!(b.find_move(mv(4, 7, 2, 7)).is_some());
+    }
+    #[test]
+    fn rook_capture_revokes_castling() {
+        let mut b = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        b.make_move(mv(7, 7, 7, 0)).unwrap();
+        assert!(!b.castling_rights.king_side(PieceColour::WHITE));
+        assert!(!b.castling_rights.king_side(PieceColour::BLACK));
+        assert!(b.castling_rights.queen_side(PieceColour::BLACK));
+    }
+    #[test]
+    fn clocks() {
+        let mut b = Board::from_fen("4k3/8/8/8/8/8/4P3/4K1N1 w - - 12 40").unwrap();
+        assert_eq!((b.halfmove_clock, b.fullmove_number), (12, 40));
+
+        b.make_move(mv(6, 7, 5, 5)).unwrap();
+        assert_eq!((b.halfmove_clock, b.fullmove_number), (13, 40));
+        b.make_move(mv(4, 0, 3, 0)).unwrap();
+        assert_eq!((b.halfmove_clock, b.fullmove_number), (14, 41));
+        b.make_move(mv(4, 6, 4, 4)).unwrap();
+        assert_eq!((b.halfmove_clock, b.fullmove_number), (0, 41));
+    }
+    #[test]
+    fn seventy_five_move_rule() {
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/4K1N1 w - - 150 120").unwrap();
+        assert!(b.can_claim_fifty_move_rule());
+        assert_eq!(b.status(), GameStatus::SEVENTY_FIVE_MOVE_RULE)
+    }
+    #[test]
+    fn insufficient_material_after_capture() {
+        let mut b = Board::from_fen("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1").unwrap();
+        assert_eq!(b.status(), GameStatus::ONGOING);
+        b.make_move(mv(4, 7, 3, 6)).unwrap();
+        assert_eq!(b.status(), GameStatus::INSUFFICIENT_MATERIAL)
+    }
+    #[test]
+    fn fen_round_trip() {
+        for fen in [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 13 37",
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen)
+        }
+        // an en passant square no pawn can capture on is left out
+        let b =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(
+            b.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+    }
+    #[test]
+    fn fen_round_trip_random_games() {
+        // simple lcg so the test is repeatable
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut positions = 0;
+        while positions < 2000 {
+            let mut b: Board = STARTING_FEN.parse().unwrap();
+            for _ in 0..80 {
+                let moves = b.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                b.make(moves[(seed >> 33) as usize % moves.len()]);
+
+                let parsed: Board = b.to_string().parse().unwrap();
+                assert_eq!(parsed, b);
+                positions += 1;
+            }
+        }
+    }
+    #[test]
+    fn fen_errors() {
+        for (fen, error) in [
+            (
+                "",
+                FenError::MISSING_FIELD {
+                    field: "position",
+                    column: 0,
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3",
+                FenError::MISSING_FIELD {
+                    field: "turn",
+                    column: 19,
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - 0 1 x",
+                FenError::TOO_MANY_FIELDS { column: 30 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4X3 w - - 0 1",
+                FenError::INVALID_PIECE {
+                    chr: 'X',
+                    column: 17,
+                },
+            ),
+            (
+                "4k4/8/8/8/8/8/8/4K3 w - - 0 1",
+                FenError::RANK_OVERFLOW { column: 2 },
+            ),
+            (
+                "4k3/8/8/8/7/8/8/4K3 w - - 0 1",
+                FenError::RANK_UNDERFLOW { column: 11 },
+            ),
+            (
+                "4k3/8/8/8/8/8/4K3 w - - 0 1",
+                FenError::WRONG_RANK_COUNT { column: 17 },
+            ),
+            (
+                "8/8/8/8/8/8/8/4K3 w - - 0 1",
+                FenError::MISSING_KING {
+                    colour: PieceColour::BLACK,
+                    column: 0,
+                },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4KK2 w - - 0 1",
+                FenError::EXTRA_KING {
+                    colour: PieceColour::WHITE,
+                    column: 18,
+                },
+            ),
+            (
+                "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+                FenError::PAWN_ON_BACK_RANK { column: 0 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+                FenError::INVALID_TURN { column: 20 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w KK - 0 1",
+                FenError::INVALID_CASTLING { column: 23 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - e3 0 1",
+                FenError::INVALID_EN_PASSANT { column: 24 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - e6 0 1",
+                FenError::INVALID_EN_PASSANT { column: 24 },
+            ),
+            (
+                // a black pawn on e2 couldn't have just double pushed
+                "4k3/8/8/8/8/8/4p3/K7 w - e3 0 1",
+                FenError::INVALID_EN_PASSANT { column: 25 },
+            ),
+            (
+                "4k3/8/8/8/4P3/8/8/K7 w - e3 0 1",
+                FenError::INVALID_EN_PASSANT { column: 25 },
+            ),
+            (
+                "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+                FenError::INVALID_CLOCK { column: 26 },
+            ),
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap_err(), error, "{}", fen)
+        }
+    }
+    #[test]
+    fn en_passant_square() {
+        let b = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(b.en_passant_ind, Some(coordinate_to_index(3, 2)));
+        assert!(b.find_move(mv(4, 3, 3, 2)).is_some())
+    }
+    #[test]
+    fn move_flags() {
+        let b = Board::from_fen("r3k2r/8/8/3pP3/8/8/P7/R3K2R w KQq d6 0 1").unwrap();
+        assert!(b.find_move(mv(4, 7, 6, 7)).unwrap().is_castle());
+        assert!(b.find_move(mv(0, 6, 0, 4)).unwrap().is_double_push());
+        assert!(b.find_move(mv(7, 7, 7, 0)).unwrap().is_capture());
+        let en_passant = b.find_move(mv(4, 3, 3, 2)).unwrap();
+        assert!(en_passant.is_en_passant() && en_passant.is_capture());
+        assert_eq!(b.find_move(mv(0, 6, 0, 5)).unwrap().flags(), Move::QUIET)
+    }
+    #[test]
+    fn unmake_restores_board() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/1P6/8/8/2pP4/8/8/R3K2R b KQkq d3 5 20",
+            "r1n1k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1",
+        ] {
+            let mut b = Board::from_fen(fen).unwrap();
+            let original = b;
+            for mv in original.legal_moves() {
+                let undo = b.make(mv);
+                assert_eq!(b.hash, b.compute_hash());
+                b.unmake(mv, undo);
+                assert_eq!(b, original, "{}", mv);
+                assert_eq!(b.hash, original.hash);
+            }
+        }
+    }
+    #[test]
+    fn copies_are_independent() {
+        let b = Board::from_fen(STARTING_FEN).unwrap();
+        let mut copy = b;
+        copy.make_move(mv(4, 6, 4, 4)).unwrap();
+        assert_eq!(b, Board::from_fen(STARTING_FEN).unwrap());
+
+        let positions: std::collections::HashSet<Board> = [b, copy, b].into_iter().collect();
+        assert_eq!(positions.len(), 2)
+    }
+    #[test]
+    fn incremental_hash() {
+        let mut b = Board::from_fen("r3k2r/1P6/8/8/2p5/8/3P4/R3K2R w KQkq - 0 1").unwrap();
+        // double push, en passant, castling and promotion with capture
+        b.make_move(mv(3, 6, 3, 4)).unwrap();
+        b.make_move(mv(2, 4, 3, 5)).unwrap();
+        b.make_move(mv(4, 7, 6, 7)).unwrap();
+        b.make_move(mv(7, 0, 7, 1)).unwrap();
+        b.make_move(Move::from_squares(
+            coordinate_to_index(1, 1),
+            coordinate_to_index(0, 0),
+            Some(PieceType::QUEEN),
+        ))
+        .unwrap();
+        assert_eq!(b.turn, PieceColour::BLACK);
+        assert_eq!(b.hash, b.compute_hash())
+    }
+    #[test]
+    fn transposition_hash() {
+        let mut a = Board::from_fen(STARTING_FEN).unwrap();
+        a.make_move(mv(6, 7, 5, 5)).unwrap();
+        a.make_move(mv(6, 0, 5, 2)).unwrap();
+        a.make_move(mv(1, 7, 2, 5)).unwrap();
+        let mut b = Board::from_fen(STARTING_FEN).unwrap();
+        b.make_move(mv(1, 7, 2, 5)).unwrap();
+        b.make_move(mv(6, 0, 5, 2)).unwrap();
+        b.make_move(mv(6, 7, 5, 5)).unwrap();
+        assert_eq!(a.hash, b.hash)
+    }
+    #[test]
+    fn en_passant_square_only_when_capturable() {
+        // no black pawn can take the e4 pawn, so the square is left out
+        let mut b = Board::from_fen(STARTING_FEN).unwrap();
+        b.make_move(mv(4, 6, 4, 4)).unwrap();
+        assert_eq!(b.en_passant_ind, None);
+        let without_square =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(b.hash, without_square.hash);
+
+        // a black pawn on d4 can take it
+        let mut b = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        b.make_move(mv(4, 6, 4, 4)).unwrap();
+        assert_eq!(b.en_passant_ind, Some(coordinate_to_index(4, 5)));
+        assert_eq!(b.hash, b.compute_hash())
     }
 }
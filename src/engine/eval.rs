@@ -0,0 +1,491 @@
+use std::fmt::Display;
+use std::ops::{Add, AddAssign, Neg, Sub};
+
+use crate::{
+    bitboard::{self, SquareSet},
+    board::{coordinate_to_index, index_to_coordinate, Board},
+    PieceColour, PieceType,
+};
+
+/// hundredths of a pawn
+pub type Centipawns = i32;
+
+/// the plain value of each piece type, indexed by PieceType, for comparing trades
+pub const PIECE_VALUES: [Centipawns; 6] = [0, 900, 500, 330, 320, 100];
+
+/// a score split into a middlegame and an endgame part, which are blended by the game phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TaperedScore {
+    pub middlegame: Centipawns,
+    pub endgame: Centipawns,
+}
+
+impl TaperedScore {
+    pub const fn new(middlegame: Centipawns, endgame: Centipawns) -> Self {
+        Self {
+            middlegame,
+            endgame,
+        }
+    }
+
+    /// blends the parts, phase goes from 0 in a bare endgame to MAX_PHASE at the start
+    pub fn taper(self, phase: i32) -> Centipawns {
+        (self.middlegame * phase + self.endgame * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+
+    fn times(self, count: i32) -> Self {
+        Self::new(self.middlegame * count, self.endgame * count)
+    }
+}
+
+impl Add for TaperedScore {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.middlegame + rhs.middlegame, self.endgame + rhs.endgame)
+    }
+}
+
+impl AddAssign for TaperedScore {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for TaperedScore {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.middlegame - rhs.middlegame, self.endgame - rhs.endgame)
+    }
+}
+
+impl Neg for TaperedScore {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.middlegame, -self.endgame)
+    }
+}
+
+/// the phase with all the pieces on the board
+pub const MAX_PHASE: i32 = 24;
+/// how much each piece type counts towards the phase, indexed by PieceType
+const PHASE_WEIGHTS: [i32; 6] = [0, 4, 2, 1, 1, 0];
+
+/// indexed by PieceType
+const MATERIAL: [TaperedScore; 6] = [
+    TaperedScore::new(0, 0),
+    TaperedScore::new(1025, 936),
+    TaperedScore::new(477, 512),
+    TaperedScore::new(365, 297),
+    TaperedScore::new(337, 281),
+    TaperedScore::new(82, 94),
+];
+
+/// bonus per square a piece can move to, indexed by PieceType
+const MOBILITY: [TaperedScore; 6] = [
+    TaperedScore::new(0, 0),
+    TaperedScore::new(1, 2),
+    TaperedScore::new(2, 4),
+    TaperedScore::new(5, 5),
+    TaperedScore::new(4, 4),
+    TaperedScore::new(0, 0),
+];
+
+/// penalty for each pawn on a file after the first
+const DOUBLED_PAWN: TaperedScore = TaperedScore::new(-10, -20);
+/// penalty for a pawn with no friendly pawns on the files next to it
+const ISOLATED_PAWN: TaperedScore = TaperedScore::new(-10, -15);
+/// bonus for a pawn no enemy pawn can stop, indexed by how many ranks it has advanced
+const PASSED_PAWN: [TaperedScore; 8] = [
+    TaperedScore::new(0, 0),
+    TaperedScore::new(5, 10),
+    TaperedScore::new(5, 15),
+    TaperedScore::new(10, 25),
+    TaperedScore::new(20, 45),
+    TaperedScore::new(35, 75),
+    TaperedScore::new(60, 120),
+    TaperedScore::new(0, 0),
+];
+
+/// bonus for each pawn in front of the king
+const PAWN_SHIELD: TaperedScore = TaperedScore::new(10, 0);
+/// penalty for each square next to the king that the enemy attacks
+const KING_ZONE_ATTACK: TaperedScore = TaperedScore::new(-8, 0);
+
+// piece-square tables from white's point of view, starting at a8 like board indices.
+// black uses the same tables flipped vertically
+#[rustfmt::skip]
+const PAWN_MIDDLEGAME: [Centipawns; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+#[rustfmt::skip]
+const PAWN_ENDGAME: [Centipawns; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    15,  15,  15,  15,  15,  15,  15,  15,
+     5,   5,   5,   5,   5,   5,   5,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+#[rustfmt::skip]
+const KNIGHT_TABLE: [Centipawns; 64] = [
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   0,   0,   0, -20, -40,
+   -30,   0,  10,  15,  15,  10,   0, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   0,  15,  20,  20,  15,   0, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+];
+#[rustfmt::skip]
+const BISHOP_TABLE: [Centipawns; 64] = [
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,  10,  10,   5,   0, -10,
+   -10,   5,   5,  10,  10,   5,   5, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,  10,  10,  10,  10,  10,  10, -10,
+   -10,   5,   0,   0,   0,   0,   5, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+];
+#[rustfmt::skip]
+const ROOK_TABLE: [Centipawns; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+#[rustfmt::skip]
+const QUEEN_TABLE: [Centipawns; 64] = [
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+     0,   0,   5,   5,   5,   5,   0,  -5,
+   -10,   5,   5,   5,   5,   5,   0, -10,
+   -10,   0,   5,   0,   0,   0,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+#[rustfmt::skip]
+const KING_MIDDLEGAME: [Centipawns; 64] = [
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -20, -30, -30, -40, -40, -30, -30, -20,
+   -10, -20, -20, -20, -20, -20, -20, -10,
+    20,  20,   0,   0,   0,   0,  20,  20,
+    20,  30,  10,   0,   0,  10,  30,  20,
+];
+#[rustfmt::skip]
+const KING_ENDGAME: [Centipawns; 64] = [
+   -50, -40, -30, -20, -20, -30, -40, -50,
+   -30, -20, -10,   0,   0, -10, -20, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -30,   0,   0,   0,   0, -30, -30,
+   -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+/// indexed by PieceType
+const MIDDLEGAME_TABLES: [&[Centipawns; 64]; 6] = [
+    &KING_MIDDLEGAME,
+    &QUEEN_TABLE,
+    &ROOK_TABLE,
+    &BISHOP_TABLE,
+    &KNIGHT_TABLE,
+    &PAWN_MIDDLEGAME,
+];
+/// indexed by PieceType
+const ENDGAME_TABLES: [&[Centipawns; 64]; 6] = [
+    &KING_ENDGAME,
+    &QUEEN_TABLE,
+    &ROOK_TABLE,
+    &BISHOP_TABLE,
+    &KNIGHT_TABLE,
+    &PAWN_ENDGAME,
+];
+
+/// the evaluation split into its terms, each in centipawns from white's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalBreakdown {
+    pub material: Centipawns,
+    pub piece_squares: Centipawns,
+    pub mobility: Centipawns,
+    pub pawn_structure: Centipawns,
+    pub king_safety: Centipawns,
+    /// from 0 in a bare endgame to MAX_PHASE with all the pieces on the board
+    pub phase: i32,
+}
+
+impl EvalBreakdown {
+    /// the sum of the terms
+    pub fn total(&self) -> Centipawns {
+        self.material + self.piece_squares + self.mobility + self.pawn_structure + self.king_safety
+    }
+}
+
+impl Display for EvalBreakdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pawns = |score: Centipawns| score as f64 / 100.0;
+        writeln!(f, "material        {:+6.2}", pawns(self.material))?;
+        writeln!(f, "piece squares   {:+6.2}", pawns(self.piece_squares))?;
+        writeln!(f, "mobility        {:+6.2}", pawns(self.mobility))?;
+        writeln!(f, "pawn structure  {:+6.2}", pawns(self.pawn_structure))?;
+        writeln!(f, "king safety     {:+6.2}", pawns(self.king_safety))?;
+        writeln!(f, "total           {:+6.2}", pawns(self.total()))?;
+        write!(f, "phase           {}/{}", self.phase, MAX_PHASE)
+    }
+}
+
+/// scores the position in centipawns from the point of view of the side to move
+pub fn evaluate(board: &Board) -> Centipawns {
+    let score = evaluate_breakdown(board).total();
+    match board.turn {
+        PieceColour::WHITE => score,
+        PieceColour::BLACK => -score,
+    }
+}
+
+/// scores each term of the evaluation from white's point of view
+pub fn evaluate_breakdown(board: &Board) -> EvalBreakdown {
+    let phase = game_phase(board);
+    // white's score minus black's
+    let term = |score: fn(&Board, PieceColour) -> TaperedScore| {
+        (score(board, PieceColour::WHITE) - score(board, PieceColour::BLACK)).taper(phase)
+    };
+
+    EvalBreakdown {
+        material: term(material),
+        piece_squares: term(piece_squares),
+        mobility: term(mobility),
+        pawn_structure: term(pawn_structure),
+        king_safety: term(king_safety),
+        phase,
+    }
+}
+
+/// how much of the material is left, see MAX_PHASE. promotions can't take it past MAX_PHASE
+fn game_phase(board: &Board) -> i32 {
+    let phase: i32 = PieceType::PIECES
+        .iter()
+        .map(|&piece_type| {
+            let count = (board.pieces(PieceColour::WHITE, piece_type)
+                | board.pieces(PieceColour::BLACK, piece_type))
+            .len() as i32;
+            PHASE_WEIGHTS[piece_type as usize] * count
+        })
+        .sum();
+    phase.min(MAX_PHASE)
+}
+
+fn material(board: &Board, colour: PieceColour) -> TaperedScore {
+    let mut score = TaperedScore::default();
+    for piece_type in PieceType::PIECES {
+        score += MATERIAL[piece_type as usize].times(board.pieces(colour, piece_type).len() as i32);
+    }
+    score
+}
+
+fn piece_squares(board: &Board, colour: PieceColour) -> TaperedScore {
+    let mut score = TaperedScore::default();
+    for piece_type in PieceType::PIECES {
+        for ind in board.pieces(colour, piece_type) {
+            // the tables are from white's point of view, so black's squares are flipped
+            let square = match colour {
+                PieceColour::WHITE => ind,
+                PieceColour::BLACK => ind ^ 56,
+            };
+            score += TaperedScore::new(
+                MIDDLEGAME_TABLES[piece_type as usize][square],
+                ENDGAME_TABLES[piece_type as usize][square],
+            );
+        }
+    }
+    score
+}
+
+/// rewards pieces for the squares they can move to
+fn mobility(board: &Board, colour: PieceColour) -> TaperedScore {
+    let occupied = board.occupied();
+    let own = board.colour_pieces(colour);
+    let mut score = TaperedScore::default();
+    for piece_type in [
+        PieceType::QUEEN,
+        PieceType::ROOK,
+        PieceType::BISHOP,
+        PieceType::KNIGHT,
+    ] {
+        for ind in board.pieces(colour, piece_type) {
+            let attacks = match piece_type {
+                PieceType::QUEEN => bitboard::queen_attacks(ind, occupied),
+                PieceType::ROOK => bitboard::rook_attacks(ind, occupied),
+                PieceType::BISHOP => bitboard::bishop_attacks(ind, occupied),
+                _ => bitboard::knight_attacks(ind),
+            };
+            score += MOBILITY[piece_type as usize].times((attacks & !own).len() as i32);
+        }
+    }
+    score
+}
+
+/// the squares on a file
+fn file_set(file: u8) -> SquareSet {
+    SquareSet(0x0101_0101_0101_0101 << file)
+}
+
+/// penalises doubled and isolated pawns and rewards passed pawns
+fn pawn_structure(board: &Board, colour: PieceColour) -> TaperedScore {
+    let pawns = board.pieces(colour, PieceType::PAWN);
+    let enemy_pawns = board.pieces(!colour, PieceType::PAWN);
+    let mut score = TaperedScore::default();
+
+    for file in 0..8 {
+        let on_file = (pawns & file_set(file)).len() as i32;
+        if on_file > 1 {
+            score += DOUBLED_PAWN.times(on_file - 1);
+        }
+    }
+
+    for ind in pawns {
+        let (file, rank) = index_to_coordinate(ind);
+        let neighbours = (file.saturating_sub(1)..=(file + 1).min(7))
+            .filter(|&other| other != file)
+            .fold(SquareSet::EMPTY, |set, other| set | file_set(other));
+        if (pawns & neighbours).is_empty() {
+            score += ISOLATED_PAWN;
+        }
+
+        // passed if no enemy pawn is in front of it on its own or a neighbouring file
+        let is_passed = !(enemy_pawns & (neighbours | file_set(file))).any(|enemy| {
+            let (_, enemy_rank) = index_to_coordinate(enemy);
+            match colour {
+                PieceColour::WHITE => enemy_rank < rank,
+                PieceColour::BLACK => enemy_rank > rank,
+            }
+        });
+        if is_passed {
+            let advanced = match colour {
+                PieceColour::WHITE => 7 - rank,
+                PieceColour::BLACK => rank,
+            };
+            score += PASSED_PAWN[advanced as usize];
+        }
+    }
+
+    score
+}
+
+/// rewards pawns sheltering the king and penalises enemy attacks next to it
+fn king_safety(board: &Board, colour: PieceColour) -> TaperedScore {
+    let (file, rank) = match board.king_square(colour) {
+        Some(square) => square,
+        None => return TaperedScore::default(),
+    };
+    let king = coordinate_to_index(file, rank);
+    let mut score = TaperedScore::default();
+
+    // the pawns on the two ranks in front of the king, on its file and the files next to it
+    let forward: [i32; 2] = match colour {
+        PieceColour::WHITE => [-1, -2],
+        PieceColour::BLACK => [1, 2],
+    };
+    let pawns = board.pieces(colour, PieceType::PAWN);
+    for shield_file in file.saturating_sub(1)..=(file + 1).min(7) {
+        let sheltered = forward.iter().any(|&step| {
+            let shield_rank = rank as i32 + step;
+            (0..8).contains(&shield_rank)
+                && pawns.contains(coordinate_to_index(shield_file, shield_rank as u8))
+        });
+        if sheltered {
+            score += PAWN_SHIELD;
+        }
+    }
+
+    let zone = bitboard::king_attacks(king);
+    let attacked = board.attacked_squares(!colour);
+    score += KING_ZONE_ATTACK.times((zone & attacked).len() as i32);
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_FEN;
+
+    fn breakdown(fen: &str) -> EvalBreakdown {
+        evaluate_breakdown(&Board::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn symmetric() {
+        let start = breakdown(STARTING_FEN);
+        assert_eq!(start.total(), 0);
+        assert_eq!(start.phase, MAX_PHASE);
+
+        // the same position with the colours swapped scores the same for the side to move
+        let board =
+            Board::from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4")
+                .unwrap();
+        let mirrored =
+            Board::from_fen("rnbqk2r/pppp1ppp/5n2/2b1p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R b KQkq - 4 4")
+                .unwrap();
+        assert_eq!(evaluate(&board), evaluate(&mirrored))
+    }
+    #[test]
+    fn material_and_phase() {
+        // white is a rook up, so black to move is losing
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        let eval = evaluate_breakdown(&board);
+        assert_eq!(eval.phase, 2);
+        assert_eq!(eval.material, TaperedScore::new(477, 512).taper(2));
+        assert_eq!(evaluate(&board), -eval.total())
+    }
+    #[test]
+    fn pawn_structure() {
+        // white's doubled, isolated c pawns against black's healthy a and b pawns
+        let eval = breakdown("4k3/pp6/8/8/8/2P5/2P5/4K3 w - - 0 1");
+        assert!(eval.pawn_structure < 0);
+
+        // a passed pawn on the seventh is worth more than one on the third
+        let far = breakdown("4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        let near = breakdown("4k3/8/8/8/8/P7/8/4K3 w - - 0 1");
+        assert!(far.pawn_structure > near.pawn_structure)
+    }
+    #[test]
+    fn mobility_and_king_safety() {
+        // a knight in the centre moves more than one in the corner
+        let centre = breakdown("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        let corner = breakdown("4k3/8/8/8/8/8/8/N3K3 w - - 0 1");
+        assert!(centre.mobility > corner.mobility);
+
+        // castled behind pawns against a king with no shelter
+        let eval = breakdown("rnbq1rk1/8/8/8/8/8/5PPP/RNBQ1RK1 w - - 0 1");
+        assert!(eval.king_safety > 0)
+    }
+    #[test]
+    fn display() {
+        let text = breakdown(STARTING_FEN).to_string();
+        assert!(text.starts_with("material         +0.00\n"));
+        assert!(text.ends_with("phase           24/24"))
+    }
+}
@@ -0,0 +1,571 @@
+mod eval;
+mod ordering;
+mod see;
+mod tt;
+
+pub use eval::{evaluate, evaluate_breakdown, Centipawns, EvalBreakdown, PIECE_VALUES};
+pub use ordering::{MoveOrdering, Stage, StagedMoves};
+pub use tt::{Bound, TranspositionTable};
+
+use std::time::{Duration, Instant};
+
+use crate::{board::Board, moves::Move};
+
+/// the score of being checkmated right now. mates further away score closer to zero
+pub const MATE: Centipawns = 30_000;
+/// bigger than any score
+const INFINITY: Centipawns = 32_000;
+/// the deepest the search goes, however long it is allowed to run
+pub const MAX_DEPTH: u32 = 64;
+
+/// when to stop searching. the search stops at the first limit reached,
+/// but always finishes depth 1 so there is a move to play
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Self::default()
+        }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        Self {
+            time: Some(time),
+            ..Self::default()
+        }
+    }
+}
+
+/// what the search found at the last depth it finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// None if there are no legal moves
+    pub best_move: Option<Move>,
+    /// centipawns from the point of view of the side to move, or a mate score near MATE
+    pub score: Centipawns,
+    /// the line both sides are expected to play, starting with best_move
+    pub pv: Vec<Move>,
+    /// positions visited over all depths
+    pub nodes: u64,
+    pub depth: u32,
+    /// how full the transposition table is, in thousandths
+    pub hashfull: u32,
+}
+
+impl SearchResult {
+    /// gets the number of moves until mate, negative if the side to move is getting mated
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score.abs() < MATE - MAX_DEPTH as i32 {
+            return None;
+        }
+        let moves = (MATE - self.score.abs() + 1) / 2;
+        Some(moves * self.score.signum())
+    }
+
+    /// writes the score from the point of view of the side to move, eg. "+0.35" or "#-2"
+    pub fn score_string(&self) -> String {
+        match self.mate_in() {
+            Some(moves) => format!("#{}", moves),
+            None => format!("{:+.2}", self.score as f64 / 100.0),
+        }
+    }
+
+    /// writes the principal variation in standard algebraic notation
+    pub fn pv_san(&self, board: &Board) -> String {
+        let mut board = *board;
+        let mut moves = Vec::new();
+        for &mv in &self.pv {
+            moves.push(mv.to_san(&board));
+            board.make(mv);
+        }
+        moves.join(" ")
+    }
+}
+
+/// finds the best move with iterative deepening, using a new transposition table
+pub fn search(board: &Board, limits: SearchLimits) -> SearchResult {
+    search_with_progress(
+        board,
+        limits,
+        &mut TranspositionTable::default(),
+        &[],
+        |_| (),
+    )
+}
+
+/// finds the best move with iterative deepening, reporting the result of each finished depth.
+/// the table can be kept between searches, eg. over a game. history is the hashes of the
+/// positions the game went through before this one, oldest first, so repetitions can be seen
+pub fn search_with_progress(
+    board: &Board,
+    limits: SearchLimits,
+    tt: &mut TranspositionTable,
+    history: &[u64],
+    progress: impl FnMut(&SearchResult),
+) -> SearchResult {
+    tt.new_search();
+    let mut searcher = Searcher {
+        limits,
+        start: Instant::now(),
+        nodes: 0,
+        can_stop: false,
+        stopped: false,
+        path: history.to_vec(),
+        root_index: history.len(),
+        played: Vec::new(),
+        ordering: MoveOrdering::new(),
+        tt,
+    };
+    searcher.iterate(*board, progress)
+}
+
+/// the state of one search
+struct Searcher<'a> {
+    limits: SearchLimits,
+    start: Instant,
+    nodes: u64,
+    /// false until depth 1 has finished
+    can_stop: bool,
+    /// set when a limit is reached, the unfinished depth is thrown away
+    stopped: bool,
+    /// hashes of the positions played before the current position, for repetitions.
+    /// starts with the game's positions before the root
+    path: Vec<u64>,
+    /// where the root's position is in path once a move has been made from it
+    root_index: usize,
+    /// the moves between the root and the current position
+    played: Vec<Move>,
+    ordering: MoveOrdering,
+    tt: &'a mut TranspositionTable,
+}
+
+impl Searcher<'_> {
+    fn iterate(
+        &mut self,
+        mut board: Board,
+        mut progress: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            pv: Vec::new(),
+            nodes: 0,
+            depth: 0,
+            hashfull: 0,
+        };
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+
+        for depth in 1..=max_depth {
+            let mut pv = Vec::new();
+            let hint = result.pv.clone();
+            let score = self.negamax(&mut board, depth, 0, -INFINITY, INFINITY, &hint, &mut pv);
+            if self.stopped {
+                break;
+            }
+
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                score,
+                pv,
+                nodes: self.nodes,
+                depth,
+                hashfull: self.tt.hashfull(),
+            };
+            progress(&result);
+            self.can_stop = true;
+
+            // searching deeper won't change the result once the game is decided
+            if result.best_move.is_none() || result.mate_in().is_some() {
+                break;
+            }
+        }
+
+        result.nodes = self.nodes;
+        result.hashfull = self.tt.hashfull();
+        result
+    }
+
+    /// checks if a search limit has been reached
+    fn should_stop(&self) -> bool {
+        if !self.can_stop {
+            return false;
+        }
+        if self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes) {
+            return true;
+        }
+        // checking the clock is slow, so only do it every so often
+        self.nodes.is_multiple_of(1024)
+            && self
+                .limits
+                .time
+                .is_some_and(|time| self.start.elapsed() >= time)
+    }
+
+    /// checks if the position is drawn by repetition, the fifty move rule or lack of material.
+    /// one repetition inside the search is enough, since the side that can avoid it would have
+    /// done so. a position from before the root has to have come up twice, so that reaching
+    /// it again makes a threefold repetition
+    fn is_draw(&self, board: &Board) -> bool {
+        if board.halfmove_clock >= 100 || board.material().is_insufficient() {
+            return true;
+        }
+
+        let mut earlier = 0;
+        for (index, &hash) in self
+            .path
+            .iter()
+            .enumerate()
+            .rev()
+            .take(board.halfmove_clock as usize)
+        {
+            if hash == board.hash {
+                if index >= self.root_index {
+                    return true;
+                }
+                earlier += 1;
+                if earlier >= 2 {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// scores the position from the side to move's point of view, searching depth more plies.
+    /// the best line found is written to pv. hint is the principal variation of the last depth,
+    /// which is searched first while the search follows it
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: u32,
+        mut alpha: Centipawns,
+        beta: Centipawns,
+        hint: &[Move],
+        pv: &mut Vec<Move>,
+    ) -> Centipawns {
+        self.nodes += 1;
+        if self.should_stop() {
+            self.stopped = true;
+            return 0;
+        }
+        if ply > 0 && self.is_draw(board) {
+            return 0;
+        }
+
+        // a result from a search at least as deep can be used straight away,
+        // except at the root, which needs a move
+        let entry = self.tt.probe(board.hash, ply);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth as u32 >= depth) {
+            let usable = match entry.bound {
+                Bound::EXACT => true,
+                Bound::LOWER => entry.score >= beta,
+                Bound::UPPER => entry.score <= alpha,
+            };
+            if usable {
+                return entry.score;
+            }
+        }
+
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        // the hinted move first, or the best move from the table
+        let hash_move = hint
+            .first()
+            .copied()
+            .or(entry.and_then(|entry| entry.best_move));
+        let mut moves = board.staged_moves(hash_move);
+        let colour = board.turn;
+        let previous = self.played.last().copied();
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        let mut searched = 0;
+        // quiet moves that didn't cause a cutoff, to lower their history
+        let mut quiets_tried = Vec::new();
+        while let Some(mv) = moves.next_move(board, |mv| {
+            self.ordering.quiet_score(mv, colour, ply, previous)
+        }) {
+            let child_hint = match hint.first() {
+                Some(&hinted) if hinted == mv => &hint[1..],
+                _ => &[],
+            };
+            let mut child_pv = Vec::new();
+
+            self.path.push(board.hash);
+            self.played.push(mv);
+            let undo = board.make(mv);
+            let score = -self.negamax(
+                board,
+                depth - 1,
+                ply + 1,
+                -beta,
+                -alpha,
+                child_hint,
+                &mut child_pv,
+            );
+            board.unmake(mv, undo);
+            self.played.pop();
+            self.path.pop();
+
+            if self.stopped {
+                return 0;
+            }
+            searched += 1;
+            let quiet = !ordering::is_noisy(mv);
+            if quiet {
+                quiets_tried.push(mv);
+            }
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend(child_pv);
+            }
+            if alpha >= beta {
+                if quiet {
+                    self.ordering
+                        .update(mv, colour, ply, depth, previous, &quiets_tried);
+                }
+                break;
+            }
+        }
+
+        if searched == 0 {
+            return if board.is_in_check(colour) {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+
+        let bound = if best <= original_alpha {
+            Bound::UPPER
+        } else if best >= beta {
+            Bound::LOWER
+        } else {
+            Bound::EXACT
+        };
+        self.tt
+            .store(board.hash, ply, depth, best, bound, best_move);
+
+        best
+    }
+
+    /// searches captures and promotions until the position is quiet, so the evaluation isn't
+    /// taken in the middle of an exchange. the side to move can also stand pat and take the
+    /// static evaluation, unless it is in check, when every move is searched
+    fn quiescence(
+        &mut self,
+        board: &mut Board,
+        ply: u32,
+        mut alpha: Centipawns,
+        beta: Centipawns,
+    ) -> Centipawns {
+        self.nodes += 1;
+        if self.should_stop() {
+            self.stopped = true;
+            return 0;
+        }
+        if self.is_draw(board) {
+            return 0;
+        }
+        if ply >= MAX_DEPTH {
+            return evaluate(board);
+        }
+
+        // in check every move is searched, otherwise the side to move can stand pat
+        // and only try captures that don't lose material
+        let in_check = board.is_in_check(board.turn);
+        let mut best = -INFINITY;
+        let mut moves = if in_check {
+            board.staged_moves(None)
+        } else {
+            let stand_pat = evaluate(board);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            best = stand_pat;
+            alpha = alpha.max(stand_pat);
+            board.staged_captures()
+        };
+
+        while let Some(mv) = moves.next_move(board, |_| 0) {
+            self.path.push(board.hash);
+            self.played.push(mv);
+            let undo = board.make(mv);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.unmake(mv, undo);
+            self.played.pop();
+            self.path.pop();
+
+            if self.stopped {
+                return 0;
+            }
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        // no way out of check
+        if in_check && best == -INFINITY {
+            return -MATE + ply as i32;
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_FEN;
+
+    fn best_move(fen: &str, depth: u32) -> SearchResult {
+        search(&Board::from_fen(fen).unwrap(), SearchLimits::depth(depth))
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let result = best_move(fen, 3);
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(result.pv_san(&board), "Ra8#");
+        assert_eq!(result.mate_in(), Some(1));
+        assert_eq!(result.score_string(), "#1")
+    }
+    #[test]
+    fn mate_in_two() {
+        // a rook cuts off the seventh rank, then the other mates on the eighth
+        let result = best_move("7k/8/R7/1R6/8/8/8/K7 w - - 0 1", 4);
+        assert_eq!(result.mate_in(), Some(2));
+        assert_eq!(result.pv.len(), 3)
+    }
+    #[test]
+    fn wins_material() {
+        // the black queen is hanging
+        let fen = "4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1";
+        let board = Board::from_fen(fen).unwrap();
+        let result = best_move(fen, 2);
+        assert_eq!(result.best_move.unwrap().to_san(&board), "Rxd5");
+        assert!(result.score > 400)
+    }
+    #[test]
+    fn avoids_being_mated() {
+        let mut board = Board::from_fen(STARTING_FEN).unwrap();
+        for san in ["f3", "e5", "g4"] {
+            board.make(board.parse_san(san).unwrap());
+        }
+        let result = search(&board, SearchLimits::depth(2));
+        assert_eq!(result.pv_san(&board), "Qh4#");
+        assert_eq!(result.score_string(), "#1");
+
+        // white to move after 1. f3 e5, g4 would lose at once
+        let mut board = Board::from_fen(STARTING_FEN).unwrap();
+        for san in ["f3", "e5"] {
+            board.make(board.parse_san(san).unwrap());
+        }
+        let result = search(&board, SearchLimits::depth(3));
+        assert_ne!(result.best_move.unwrap().to_san(&board), "g4")
+    }
+    #[test]
+    fn quiescence() {
+        // taking the pawn on d5 loses the queen to the pawn on c6 just past the horizon
+        let fen = "4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1";
+        let board = Board::from_fen(fen).unwrap();
+        let result = best_move(fen, 1);
+        assert_ne!(result.best_move.unwrap().to_san(&board), "Qxd5");
+        assert!(result.score > 0)
+    }
+    #[test]
+    fn repetitions_before_the_root() {
+        // black is a queen down, but the position after Kg8 has already come up twice
+        let mut board = Board::from_fen("7k/8/8/8/8/8/8/KQ6 b - - 0 1").unwrap();
+        let mut history = Vec::new();
+        for san in ["Kg8", "Qc1", "Kh8", "Qb1"].repeat(2) {
+            history.push(board.hash);
+            board.make(board.parse_san(san).unwrap());
+        }
+
+        let limits = SearchLimits::depth(3);
+        let result = search_with_progress(
+            &board,
+            limits,
+            &mut TranspositionTable::new(1),
+            &history,
+            |_| (),
+        );
+        assert_eq!(result.best_move.unwrap().to_san(&board), "Kg8");
+        assert_eq!(result.score, 0);
+
+        // without the game's positions the search can't see the draw
+        assert!(search(&board, limits).score < -500);
+        // and one earlier occurrence isn't enough
+        let result = search_with_progress(
+            &board,
+            limits,
+            &mut TranspositionTable::new(1),
+            &history[4..],
+            |_| (),
+        );
+        assert!(result.score < -500)
+    }
+    #[test]
+    fn no_legal_moves() {
+        let result = best_move("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, 0)
+    }
+    #[test]
+    fn limits() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        let result = search(&board, SearchLimits::nodes(2000));
+        assert!(result.best_move.is_some());
+        assert!(result.nodes <= 2000 || result.depth == 1);
+
+        let result = search(&board, SearchLimits::time(Duration::from_millis(50)));
+        assert!(result.best_move.is_some());
+
+        let result = search(&board, SearchLimits::depth(3));
+        assert_eq!(result.depth, 3);
+        assert_eq!(result.pv.first(), result.best_move.as_ref())
+    }
+    #[test]
+    fn transposition_table() {
+        let board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let mut tt = TranspositionTable::new(1);
+        let first = search_with_progress(&board, SearchLimits::depth(3), &mut tt, &[], |_| ());
+        assert!(first.hashfull > 0);
+
+        // searching again with the table kept is cheaper and agrees on the score
+        let again = search_with_progress(&board, SearchLimits::depth(3), &mut tt, &[], |_| ());
+        assert!(again.nodes < first.nodes);
+        assert_eq!(again.score, first.score);
+        assert_eq!(again.best_move, first.best_move)
+    }
+}
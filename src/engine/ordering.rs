@@ -0,0 +1,387 @@
+use crate::{
+    board::Board,
+    engine::{MAX_DEPTH, PIECE_VALUES},
+    moves::Move,
+    PieceColour, PieceType,
+};
+
+/// the groups of moves the staged generator hands out, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    HASH_MOVE,
+    /// the moves are generated on the way into the next stage
+    GENERATE,
+    /// captures and queen promotions that don't lose material, most valuable victim first
+    GOOD_CAPTURES,
+    /// every other move that isn't a capture, ordered by the search's heuristics
+    QUIETS,
+    /// captures that lose material by static exchange
+    BAD_CAPTURES,
+    DONE,
+}
+
+/// generates moves a group at a time, so a search that stops after the first few moves
+/// doesn't pay for generating, scoring and checking the legality of the rest
+pub struct StagedMoves {
+    stage: Stage,
+    /// handed out first, then taken
+    hash_move: Option<Move>,
+    /// the hash move once handed out, so it isn't generated again
+    skip: Option<Move>,
+    /// only hand out good captures, eg. for quiescence search
+    captures_only: bool,
+    /// moves left in each stage with their scores
+    captures: Vec<(Move, i32)>,
+    quiets: Vec<(Move, i32)>,
+    bad_captures: Vec<(Move, i32)>,
+}
+
+impl Board {
+    /// gets a generator for every legal move, starting with the hash move if it is legal
+    pub fn staged_moves(&self, hash_move: Option<Move>) -> StagedMoves {
+        StagedMoves {
+            stage: Stage::HASH_MOVE,
+            hash_move: hash_move.filter(|&mv| self.is_legal(mv)),
+            skip: None,
+            captures_only: false,
+            captures: Vec::new(),
+            quiets: Vec::new(),
+            bad_captures: Vec::new(),
+        }
+    }
+
+    /// gets a generator for the legal captures and queen promotions that don't lose material
+    pub fn staged_captures(&self) -> StagedMoves {
+        StagedMoves {
+            captures_only: true,
+            ..self.staged_moves(None)
+        }
+    }
+}
+
+impl StagedMoves {
+    /// the stage the last move came from
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    /// gets the next legal move, or None once every move has been handed out.
+    /// quiet moves are scored with quiet_score when their stage is reached
+    pub fn next_move(
+        &mut self,
+        board: &mut Board,
+        quiet_score: impl Fn(Move) -> i32,
+    ) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HASH_MOVE => match self.hash_move.take() {
+                    Some(mv) => {
+                        self.skip = Some(mv);
+                        return Some(mv);
+                    }
+                    None => self.stage = Stage::GENERATE,
+                },
+                Stage::GENERATE => {
+                    for mv in board.pseudo_legal_moves() {
+                        if Some(mv) == self.skip {
+                            continue;
+                        }
+                        if is_noisy(mv) {
+                            self.captures.push((mv, mvv_lva(board, mv)));
+                        } else if !self.captures_only {
+                            self.quiets.push((mv, 0));
+                        }
+                    }
+                    self.stage = Stage::GOOD_CAPTURES;
+                }
+                Stage::GOOD_CAPTURES => match pick_best(&mut self.captures) {
+                    Some(mv) if !wins_or_trades(board, mv) => {
+                        if !self.captures_only {
+                            self.bad_captures.push((mv, 0));
+                        }
+                    }
+                    Some(mv) if board.keeps_king_safe(mv) => return Some(mv),
+                    Some(_) => (),
+                    None if self.captures_only => self.stage = Stage::DONE,
+                    None => {
+                        for (mv, score) in &mut self.quiets {
+                            *score = quiet_score(*mv);
+                        }
+                        self.stage = Stage::QUIETS;
+                    }
+                },
+                Stage::QUIETS => match pick_best(&mut self.quiets) {
+                    Some(mv) if board.keeps_king_safe(mv) => return Some(mv),
+                    Some(_) => (),
+                    None => {
+                        // bad captures were found best first
+                        self.bad_captures.reverse();
+                        self.stage = Stage::BAD_CAPTURES;
+                    }
+                },
+                Stage::BAD_CAPTURES => match self.bad_captures.pop() {
+                    Some((mv, _)) if board.keeps_king_safe(mv) => return Some(mv),
+                    Some(_) => (),
+                    None => self.stage = Stage::DONE,
+                },
+                Stage::DONE => return None,
+            }
+        }
+    }
+}
+
+/// captures and queen promotions, the moves that change the material balance the most
+pub fn is_noisy(mv: Move) -> bool {
+    mv.is_capture() || mv.promotion() == Some(PieceType::QUEEN)
+}
+
+/// most valuable victim, least valuable attacker: bigger captures first,
+/// and of those, the ones made with the cheapest piece
+fn mvv_lva(board: &Board, mv: Move) -> i32 {
+    let victim = match board.squares[mv.to()] {
+        Some(piece) => PIECE_VALUES[piece.piece_type() as usize],
+        None if mv.is_en_passant() => PIECE_VALUES[PieceType::PAWN as usize],
+        None => 0,
+    };
+    let promotion = mv
+        .promotion()
+        .map_or(0, |piece_type| PIECE_VALUES[piece_type as usize]);
+    let attacker =
+        board.squares[mv.from()].map_or(0, |piece| PIECE_VALUES[piece.piece_type() as usize]);
+    (victim + promotion) * 16 - attacker / 10
+}
+
+/// checks if a capture doesn't lose material, only running the exchange
+/// when a more valuable piece takes a less valuable one
+fn wins_or_trades(board: &Board, mv: Move) -> bool {
+    let value = |piece_type: Option<PieceType>| piece_type.map_or(0, |t| PIECE_VALUES[t as usize]);
+    let victim = value(board.squares[mv.to()].map(|piece| piece.piece_type()));
+    let attacker = value(board.squares[mv.from()].map(|piece| piece.piece_type()));
+    victim >= attacker || mv.is_en_passant() || board.see(mv) >= 0
+}
+
+/// takes the highest scoring move out of the list. a selection sort one move at a time
+/// is cheaper than sorting, since most lists are abandoned after a few moves
+fn pick_best(moves: &mut Vec<(Move, i32)>) -> Option<Move> {
+    let (best, _) = moves
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, (_, score))| *score)?;
+    Some(moves.swap_remove(best).0)
+}
+
+/// scores above any history score, for the killer and counter moves
+const KILLER_SCORES: [i32; 2] = [3 << 20, 2 << 20];
+const COUNTER_MOVE_SCORE: i32 = 1 << 20;
+/// history scores are kept between -MAX_HISTORY and MAX_HISTORY
+const MAX_HISTORY: i32 = 1 << 16;
+
+/// what the search has learned about which quiet moves are good, to try them early
+pub struct MoveOrdering {
+    /// quiet moves that caused a beta cutoff at each ply, most recent first
+    killers: Vec<[Option<Move>; 2]>,
+    /// how often quiet moves caused cutoffs, indexed by colour, from and to square
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    /// the quiet move that last refuted each move, indexed by its from and to square
+    counter_moves: Box<[[Option<Move>; 64]; 64]>,
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        Self {
+            killers: vec![[None; 2]; MAX_DEPTH as usize + 1],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            counter_moves: Box::new([[None; 64]; 64]),
+        }
+    }
+
+    /// scores a quiet move for the side to move, ply plies from the root.
+    /// previous is the move that led to the position
+    pub fn quiet_score(
+        &self,
+        mv: Move,
+        colour: PieceColour,
+        ply: u32,
+        previous: Option<Move>,
+    ) -> i32 {
+        if let Some(killers) = self.killers.get(ply as usize) {
+            if let Some(slot) = killers.iter().position(|&killer| killer == Some(mv)) {
+                return KILLER_SCORES[slot];
+            }
+        }
+        if previous.is_some_and(|previous| self.counter_move(previous) == Some(mv)) {
+            return COUNTER_MOVE_SCORE;
+        }
+        self.history[colour as usize][mv.from()][mv.to()]
+    }
+
+    /// the quiet move that last refuted a move
+    pub fn counter_move(&self, previous: Move) -> Option<Move> {
+        self.counter_moves[previous.from()][previous.to()]
+    }
+
+    /// learns from a quiet move causing a beta cutoff at the given depth.
+    /// the quiet moves tried before it are made less likely to be tried early
+    pub fn update(
+        &mut self,
+        mv: Move,
+        colour: PieceColour,
+        ply: u32,
+        depth: u32,
+        previous: Option<Move>,
+        tried: &[Move],
+    ) {
+        if let Some(killers) = self.killers.get_mut(ply as usize) {
+            if killers[0] != Some(mv) {
+                killers[1] = killers[0];
+                killers[0] = Some(mv);
+            }
+        }
+        if let Some(previous) = previous {
+            self.counter_moves[previous.from()][previous.to()] = Some(mv);
+        }
+
+        let bonus = (depth * depth).min(400) as i32;
+        self.add_history(mv, colour, bonus);
+        for &tried in tried.iter().filter(|&&tried| tried != mv) {
+            self.add_history(tried, colour, -bonus);
+        }
+    }
+
+    /// moves a history score towards the limit of the bonus' sign,
+    /// by less the closer it already is, so scores never overflow
+    fn add_history(&mut self, mv: Move, colour: PieceColour, bonus: i32) {
+        let score = &mut self.history[colour as usize][mv.from()][mv.to()];
+        *score += bonus * 32 - *score * bonus.abs() / (MAX_HISTORY / 32);
+        *score = (*score).clamp(-MAX_HISTORY, MAX_HISTORY);
+    }
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_FEN;
+
+    /// hands out every move of a generator with the stage it came from, as san
+    fn drain(board: &Board, mut moves: StagedMoves) -> Vec<(Stage, String)> {
+        let mut scratch = *board;
+        let mut drained = Vec::new();
+        while let Some(mv) = moves.next_move(&mut scratch, |_| 0) {
+            assert_eq!(scratch, *board);
+            drained.push((moves.stage(), mv.to_san(board)));
+        }
+        drained
+    }
+
+    #[test]
+    fn every_legal_move_once() {
+        for fen in [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            // a pinned knight, then a check that stops castling
+            "4k3/8/8/8/1b6/8/3N4/4K3 w - - 0 1",
+            "4k3/8/8/8/1b6/8/8/R3K3 w Q - 0 1",
+        ] {
+            let board = Board::from_fen(fen).unwrap();
+            let mut staged: Vec<String> = drain(&board, board.staged_moves(None))
+                .into_iter()
+                .map(|(_, san)| san)
+                .collect();
+            let mut legal: Vec<String> = board
+                .legal_moves()
+                .iter()
+                .map(|mv| mv.to_san(&board))
+                .collect();
+            staged.sort();
+            legal.sort();
+            assert_eq!(staged, legal)
+        }
+    }
+    #[test]
+    fn stages_in_order() {
+        // the knight can take an undefended rook, or a pawn the queen can also take,
+        // but the pawn on c6 defends it
+        let board = Board::from_fen("4k3/8/2p5/1p1r4/8/2N5/8/1Q2K3 w - - 0 1").unwrap();
+        let hash_move = board.parse_san("Ke2").unwrap();
+        let moves = drain(&board, board.staged_moves(Some(hash_move)));
+        let stage = |wanted: Stage| -> Vec<&str> {
+            moves
+                .iter()
+                .filter(|(stage, _)| *stage == wanted)
+                .map(|(_, san)| san.as_str())
+                .collect()
+        };
+
+        assert_eq!(stage(Stage::HASH_MOVE), ["Ke2"]);
+        assert_eq!(stage(Stage::GOOD_CAPTURES), ["Nxd5"]);
+        assert!(stage(Stage::QUIETS).len() > 10);
+        assert!(!stage(Stage::QUIETS).contains(&"Ke2"));
+        // the knight loses less than the queen
+        assert_eq!(stage(Stage::BAD_CAPTURES), ["Nxb5", "Qxb5"]);
+        // stages only go forwards
+        assert!(moves.windows(2).all(|pair| pair[0].0 <= pair[1].0))
+    }
+    #[test]
+    fn captures_only() {
+        let board = Board::from_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(drain(&board, board.staged_captures()).is_empty());
+
+        let board = Board::from_fen("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(
+            drain(&board, board.staged_captures()),
+            [(Stage::GOOD_CAPTURES, "Qxd5".to_owned())]
+        );
+        // an illegal hash move is left out
+        let moves = board.staged_moves(Some(Move::from_squares(59, 3, None)));
+        assert_ne!(drain(&board, moves)[0].0, Stage::HASH_MOVE)
+    }
+    #[test]
+    fn most_valuable_victim_first() {
+        let board = Board::from_fen("4k3/8/8/2rq4/1P6/4N3/8/2R1K3 w - - 0 1").unwrap();
+        let score = |san: &str| mvv_lva(&board, board.parse_san(san).unwrap());
+        // the queen is worth more than the rook, which is better taken with the pawn
+        assert!(score("Nxd5") > score("bxc5"));
+        assert!(score("bxc5") > score("Rxc5"))
+    }
+    #[test]
+    fn killers_counters_and_history() {
+        let mut ordering = MoveOrdering::new();
+        let colour = PieceColour::WHITE;
+        let [a, b, c, d] = [(52, 36), (51, 35), (62, 45), (57, 42)]
+            .map(|(from, to)| Move::from_squares(from, to, None));
+        let previous = Move::from_squares(12, 28, None);
+
+        ordering.update(a, colour, 3, 4, Some(previous), &[b, a]);
+        ordering.update(c, colour, 3, 2, None, &[c]);
+        assert_eq!(ordering.quiet_score(c, colour, 3, None), KILLER_SCORES[0]);
+        assert_eq!(ordering.quiet_score(a, colour, 3, None), KILLER_SCORES[1]);
+        // killers only count at their own ply, where the counter move is next best
+        assert_eq!(ordering.counter_move(previous), Some(a));
+        assert_eq!(
+            ordering.quiet_score(a, colour, 4, Some(previous)),
+            COUNTER_MOVE_SCORE
+        );
+        // history remembers the cutoffs and the moves that failed to cause one
+        let history = |mv| ordering.quiet_score(mv, colour, 4, None);
+        assert!(history(a) > history(c));
+        assert!(history(c) > history(d));
+        assert!(history(d) > history(b));
+        assert_eq!(ordering.quiet_score(a, !colour, 4, None), 0)
+    }
+    #[test]
+    fn history_is_bounded() {
+        let mut ordering = MoveOrdering::new();
+        let mv = Move::from_squares(52, 36, None);
+        for _ in 0..10_000 {
+            ordering.update(mv, PieceColour::BLACK, 0, 20, None, &[]);
+        }
+        let score = ordering.quiet_score(mv, PieceColour::BLACK, 1, None);
+        assert!(score > 0 && score <= MAX_HISTORY)
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{
+    bitboard::SquareSet,
+    board::{coordinate_to_index, Board},
+    engine::{Centipawns, PIECE_VALUES},
+    moves::Move,
+    PieceColour, PieceType,
+};
+
+/// the piece types in the order they are used to recapture
+const CHEAPEST_FIRST: [PieceType; 6] = [
+    PieceType::PAWN,
+    PieceType::KNIGHT,
+    PieceType::BISHOP,
+    PieceType::ROOK,
+    PieceType::QUEEN,
+    PieceType::KING,
+];
+
+impl Board {
+    /// static exchange evaluation. works out what the side to move gains from a capture,
+    /// if both sides keep recapturing on the square with their cheapest piece while it pays off.
+    /// a quiet move scores 0, or less if the piece can be taken for free
+    pub fn see(&self, mv: Move) -> Centipawns {
+        let mut occupied = self.occupied();
+        let mut captured = match self.squares[mv.to()] {
+            Some(piece) => PIECE_VALUES[piece.piece_type() as usize],
+            None => 0,
+        };
+        if mv.is_en_passant() {
+            captured = PIECE_VALUES[PieceType::PAWN as usize];
+            let (to_file, _) = mv.end();
+            let (_, from_rank) = mv.start();
+            occupied.remove(coordinate_to_index(to_file, from_rank));
+        }
+
+        let mut moving = match self.squares[mv.from()] {
+            Some(piece) => piece.piece_type(),
+            None => return 0,
+        };
+        if let Some(promotion) = mv.promotion() {
+            captured += PIECE_VALUES[promotion as usize] - PIECE_VALUES[PieceType::PAWN as usize];
+            moving = promotion;
+        }
+
+        self.exchange(mv.from(), mv.to(), moving, captured, occupied)
+    }
+
+    /// the pieces of the given colour the opponent can win material by capturing
+    pub fn hanging_pieces(&self, colour: PieceColour) -> SquareSet {
+        let occupied = self.occupied();
+        let enemies = self.colour_pieces(!colour);
+        let mut hanging = SquareSet::EMPTY;
+
+        for ind in self.colour_pieces(colour) & !self.pieces(colour, PieceType::KING) {
+            let attackers = self.attackers_to(ind, occupied) & enemies;
+            let cheapest = CHEAPEST_FIRST.iter().find_map(|&piece_type| {
+                (attackers & self.pieces(!colour, piece_type))
+                    .first()
+                    .map(|from| (from, piece_type))
+            });
+            if let Some((from, piece_type)) = cheapest {
+                let captured = self.squares[ind]
+                    .map(|piece| PIECE_VALUES[piece.piece_type() as usize])
+                    .unwrap_or(0);
+                if self.exchange(from, ind, piece_type, captured, occupied) > 0 {
+                    hanging.insert(ind);
+                }
+            }
+        }
+
+        hanging
+    }
+
+    /// the swap algorithm: the piece on from takes whatever is worth captured on to,
+    /// then the sides take turns recapturing on to with their cheapest attacker.
+    /// either side can stop capturing when carrying on would lose material
+    fn exchange(
+        &self,
+        from: usize,
+        to: usize,
+        moving: PieceType,
+        captured: Centipawns,
+        mut occupied: SquareSet,
+    ) -> Centipawns {
+        let mover = match self.squares[from] {
+            Some(piece) => piece.colour(),
+            None => return 0,
+        };
+        // gains[i] is what the side making capture i wins if the exchange stops after it
+        let mut gains = vec![captured];
+        let mut from_set = SquareSet::from_index(from);
+        let mut attacker = moving;
+        let mut side = mover;
+
+        loop {
+            // what the side about to recapture wins if it stops after taking the piece
+            // that just captured
+            let gain = see_value(attacker) - gains[gains.len() - 1];
+            // nobody would carry on if it can only lose
+            if (-gains[gains.len() - 1]).max(gain) < 0 {
+                break;
+            }
+
+            occupied &= !from_set;
+            side = !side;
+            // sliders behind the piece that moved now see the square
+            let attackers = self.attackers_to(to, occupied) & self.colour_pieces(side);
+            let next = CHEAPEST_FIRST.iter().find_map(|&piece_type| {
+                (attackers & self.pieces(side, piece_type))
+                    .first()
+                    .map(|ind| (ind, piece_type))
+            });
+            match next {
+                // a king can't recapture onto a square the other side still attacks
+                Some((_, PieceType::KING))
+                    if !(self.attackers_to(to, occupied) & self.colour_pieces(!side))
+                        .is_empty() =>
+                {
+                    break
+                }
+                Some((ind, piece_type)) => {
+                    gains.push(gain);
+                    from_set = SquareSet::from_index(ind);
+                    attacker = piece_type;
+                }
+                None => break,
+            }
+        }
+
+        // work back from the end, each side choosing between capturing and stopping
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.len() - 1;
+            gains[previous] = -(-gains[previous]).max(last);
+        }
+        gains[0]
+    }
+}
+
+/// the value of a piece that could be captured, kings are worth more than everything else
+fn see_value(piece_type: PieceType) -> Centipawns {
+    match piece_type {
+        PieceType::KING => 20_000,
+        piece_type => PIECE_VALUES[piece_type as usize],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::index_to_name;
+
+    fn see(fen: &str, san: &str) -> Centipawns {
+        let board = Board::from_fen(fen).unwrap();
+        board.see(board.parse_san(san).unwrap())
+    }
+
+    #[test]
+    fn undefended_and_defended() {
+        // a free pawn
+        assert_eq!(
+            see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "Rxe5"),
+            100
+        );
+        // a pawn defended by a knight, with a bishop and rook behind the queen
+        let fen = "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1";
+        assert_eq!(see(fen, "Nxe5"), -220)
+    }
+    #[test]
+    fn recaptures_stop_when_they_lose() {
+        // queen takes a pawn defended by a pawn
+        assert_eq!(see("4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1", "Qxe5"), -800);
+        // pawn takes a knight defended by a queen, the queen doesn't take back into the rook
+        assert_eq!(see("4k3/8/8/3n3q/4P3/8/8/3RK3 w - - 0 1", "exd5"), 320)
+    }
+    #[test]
+    fn quiet_moves_and_promotions() {
+        let promotion_fen = "4k3/1P6/8/8/8/8/8/4K2R w - - 0 1";
+        assert_eq!(see(promotion_fen, "Rh2"), 0);
+        // moving the rook where the king can take it for free
+        let fen = "8/8/8/8/8/8/6k1/4K2R w - - 0 1";
+        assert_eq!(see(fen, "Rh2+"), -500);
+        // unless the other king defends it
+        assert_eq!(see(fen, "Rf1"), 0);
+        assert_eq!(see(promotion_fen, "b8=Q+"), 800)
+    }
+    #[test]
+    fn hanging_pieces() {
+        // the knight on c6 is attacked by a pawn and the rook on a8 by a knight
+        let board = Board::from_fen("r3k3/8/1Nn5/1P6/8/8/8/4K3 b - - 0 1").unwrap();
+        let hanging: Vec<String> = board
+            .hanging_pieces(PieceColour::BLACK)
+            .map(index_to_name)
+            .collect();
+        assert_eq!(hanging, vec!["a8", "c6"]);
+        assert!(board.hanging_pieces(PieceColour::WHITE).is_empty())
+    }
+}
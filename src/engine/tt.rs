@@ -0,0 +1,210 @@
+use std::mem;
+
+use crate::engine::{Centipawns, MATE, MAX_DEPTH};
+use crate::moves::Move;
+
+/// how a stored score relates to the real score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    EXACT,
+    /// the search failed high, so the real score is at least this
+    LOWER,
+    /// the search failed low, so the real score is at most this
+    UPPER,
+}
+
+/// what a search found out about a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    /// the full hash, to tell apart positions that share a slot
+    key: u64,
+    pub best_move: Option<Move>,
+    pub score: Centipawns,
+    pub depth: u8,
+    pub bound: Bound,
+    /// the search the entry was stored in
+    generation: u8,
+}
+
+/// a fixed-size table of search results, indexed by zobrist hash
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    /// bumped at the start of every search so old entries can be replaced first
+    generation: u8,
+}
+
+impl TranspositionTable {
+    pub const DEFAULT_MEGABYTES: usize = 16;
+
+    /// makes a table that uses about the given number of megabytes, and at least one entry
+    pub fn new(megabytes: usize) -> Self {
+        let len = (megabytes * 1024 * 1024 / mem::size_of::<Option<Entry>>()).max(1);
+        Self {
+            entries: vec![None; len],
+            generation: 0,
+        }
+    }
+
+    /// number of entries the table can hold
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.iter().all(Option::is_none)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.generation = 0;
+    }
+
+    /// marks the entries stored so far as old
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    /// looks up a position reached ply plies from the root
+    pub fn probe(&self, key: u64, ply: u32) -> Option<Entry> {
+        let entry = self.entries[self.index(key)].filter(|entry| entry.key == key)?;
+        Some(Entry {
+            score: score_from_table(entry.score, ply),
+            ..entry
+        })
+    }
+
+    /// stores the result of searching a position reached ply plies from the root.
+    /// an entry from the current search is only replaced by one searched at least as deep
+    pub fn store(
+        &mut self,
+        key: u64,
+        ply: u32,
+        depth: u32,
+        score: Centipawns,
+        bound: Bound,
+        best_move: Option<Move>,
+    ) {
+        let index = self.index(key);
+        if let Some(old) = self.entries[index] {
+            if old.key != key && old.generation == self.generation && old.depth as u32 > depth {
+                return;
+            }
+        }
+
+        self.entries[index] = Some(Entry {
+            key,
+            best_move,
+            score: score_to_table(score, ply),
+            depth: depth.min(u8::MAX as u32) as u8,
+            bound,
+            generation: self.generation,
+        });
+    }
+
+    /// how full the table is with entries from the current search, in thousandths
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.entries.len().min(1000);
+        let used = self.entries[..sample]
+            .iter()
+            .filter(|entry| entry.is_some_and(|entry| entry.generation == self.generation))
+            .count();
+        (used * 1000 / sample) as u32
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MEGABYTES)
+    }
+}
+
+/// mate scores count plies from the root, but the same position can be reached at any ply,
+/// so they are stored counting from the position itself
+fn score_to_table(score: Centipawns, ply: u32) -> Centipawns {
+    if score >= MATE - MAX_DEPTH as i32 {
+        score + ply as i32
+    } else if score <= -MATE + MAX_DEPTH as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: Centipawns, ply: u32) -> Centipawns {
+    if score >= MATE - MAX_DEPTH as i32 {
+        score - ply as i32
+    } else if score <= -MATE + MAX_DEPTH as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_and_probe() {
+        let mut tt = TranspositionTable::new(1);
+        assert!(tt.len() > 1000);
+        assert!(tt.is_empty());
+        let mv = Move::from_squares(52, 36, None);
+        tt.store(12345, 0, 4, 35, Bound::EXACT, Some(mv));
+
+        let entry = tt.probe(12345, 0).unwrap();
+        assert_eq!((entry.score, entry.depth), (35, 4));
+        assert_eq!((entry.bound, entry.best_move), (Bound::EXACT, Some(mv)));
+        // a different position in the same slot
+        assert_eq!(tt.probe(12345 + tt.len() as u64, 0), None);
+
+        tt.clear();
+        assert_eq!(tt.probe(12345, 0), None)
+    }
+    #[test]
+    fn mate_scores() {
+        let mut tt = TranspositionTable::new(1);
+        // mate in 3 plies found 2 plies from the root is mate in 1 ply from the position
+        tt.store(1, 2, 3, MATE - 3, Bound::EXACT, None);
+        assert_eq!(tt.probe(1, 2).unwrap().score, MATE - 3);
+        // reached 4 plies from the root instead, it is mate 5 plies from the root
+        assert_eq!(tt.probe(1, 4).unwrap().score, MATE - 5);
+
+        tt.store(2, 1, 3, -MATE + 6, Bound::UPPER, None);
+        assert_eq!(tt.probe(2, 3).unwrap().score, -MATE + 8)
+    }
+    #[test]
+    fn replacement() {
+        let mut tt = TranspositionTable::new(1);
+        let other = 7 + tt.len() as u64;
+        tt.store(7, 0, 6, 10, Bound::EXACT, None);
+
+        // a shallower result for another position doesn't replace a deeper one
+        tt.store(other, 0, 2, 20, Bound::EXACT, None);
+        assert!(tt.probe(7, 0).is_some());
+        // the same position is always updated
+        tt.store(7, 0, 1, 30, Bound::LOWER, None);
+        assert_eq!(tt.probe(7, 0).unwrap().score, 30);
+
+        // results from an earlier search are replaced
+        tt.store(7, 0, 6, 10, Bound::EXACT, None);
+        tt.new_search();
+        tt.store(other, 0, 2, 20, Bound::EXACT, None);
+        assert_eq!(tt.probe(7, 0), None);
+        assert!(tt.probe(other, 0).is_some())
+    }
+    #[test]
+    fn hashfull() {
+        let mut tt = TranspositionTable::new(1);
+        for key in 0..100 {
+            tt.store(key, 0, 1, 0, Bound::EXACT, None);
+        }
+        assert_eq!(tt.hashfull(), 100);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0)
+    }
+}
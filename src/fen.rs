@@ -0,0 +1,106 @@
+use std::fmt::Display;
+
+use crate::PieceColour;
+
+/// why a fen string could not be parsed. columns are 0-based offsets into the fen string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenError {
+    MISSING_FIELD {
+        field: &'static str,
+        column: usize,
+    },
+    TOO_MANY_FIELDS {
+        column: usize,
+    },
+    INVALID_PIECE {
+        chr: char,
+        column: usize,
+    },
+    /// a rank describes more than 8 squares
+    RANK_OVERFLOW {
+        column: usize,
+    },
+    /// a rank describes fewer than 8 squares
+    RANK_UNDERFLOW {
+        column: usize,
+    },
+    /// the position doesn't have exactly 8 ranks
+    WRONG_RANK_COUNT {
+        column: usize,
+    },
+    MISSING_KING {
+        colour: PieceColour,
+        column: usize,
+    },
+    EXTRA_KING {
+        colour: PieceColour,
+        column: usize,
+    },
+    PAWN_ON_BACK_RANK {
+        column: usize,
+    },
+    INVALID_TURN {
+        column: usize,
+    },
+    INVALID_CASTLING {
+        column: usize,
+    },
+    /// the en passant square is malformed or no pawn could have just double pushed past it
+    INVALID_EN_PASSANT {
+        column: usize,
+    },
+    INVALID_CLOCK {
+        column: usize,
+    },
+}
+
+impl FenError {
+    pub fn column(&self) -> usize {
+        match *self {
+            FenError::MISSING_FIELD { column, .. }
+            | FenError::TOO_MANY_FIELDS { column }
+            | FenError::INVALID_PIECE { column, .. }
+            | FenError::RANK_OVERFLOW { column }
+            | FenError::RANK_UNDERFLOW { column }
+            | FenError::WRONG_RANK_COUNT { column }
+            | FenError::MISSING_KING { column, .. }
+            | FenError::EXTRA_KING { column, .. }
+            | FenError::PAWN_ON_BACK_RANK { column }
+            | FenError::INVALID_TURN { column }
+            | FenError::INVALID_CASTLING { column }
+            | FenError::INVALID_EN_PASSANT { column }
+            | FenError::INVALID_CLOCK { column } => column,
+        }
+    }
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid fen at column {}: ", self.column())?;
+        match self {
+            FenError::MISSING_FIELD { field, .. } => write!(f, "no {}", field),
+            FenError::TOO_MANY_FIELDS { .. } => write!(f, "unexpected extra field"),
+            FenError::INVALID_PIECE { chr, .. } => write!(f, "cannot parse '{}' as a piece", chr),
+            FenError::RANK_OVERFLOW { .. } => write!(f, "rank has more than 8 squares"),
+            FenError::RANK_UNDERFLOW { .. } => write!(f, "rank has fewer than 8 squares"),
+            FenError::WRONG_RANK_COUNT { .. } => write!(f, "position must have 8 ranks"),
+            FenError::MISSING_KING { colour, .. } => write!(f, "{:?} has no king", colour),
+            FenError::EXTRA_KING { colour, .. } => write!(f, "{:?} has more than one king", colour),
+            FenError::PAWN_ON_BACK_RANK { .. } => {
+                write!(f, "pawns can't be on the first or last rank")
+            }
+            FenError::INVALID_TURN { .. } => write!(f, "turn must be 'w' or 'b'"),
+            FenError::INVALID_CASTLING { .. } => {
+                write!(f, "castling rights must be '-' or some of 'KQkq'")
+            }
+            FenError::INVALID_EN_PASSANT { .. } => write!(f, "impossible en passant square"),
+            FenError::INVALID_CLOCK { .. } => write!(f, "clocks must be whole numbers"),
+        }
+    }
+}
+
+impl From<FenError> for String {
+    fn from(value: FenError) -> Self {
+        value.to_string()
+    }
+}
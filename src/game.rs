@@ -1,23 +1,303 @@
-use crate::board::Board;
-use crate::io::{Command, IO};
-use crate::{UnitResult, STARTING_FEN};
+use std::fmt::Display;
+use std::fs;
+use std::time::Duration;
+
+use crate::board::{Board, UndoInfo};
+use crate::engine::{self, SearchLimits, TranspositionTable};
+use crate::io::{Command, HELP, IO};
+use crate::moves::Move;
+use crate::pgn::{self, Annotation, Pgn, PgnError, PgnLine, PgnMove, SEVEN_TAG_ROSTER};
+use crate::{PieceColour, UnitResult, STARTING_FEN};
+
+/// the state of a game, from the point of view of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    ONGOING,
+    /// the colour that delivered checkmate
+    CHECKMATE(PieceColour),
+    STALEMATE,
+    /// claimed after 50 moves by each side without a capture or pawn move
+    FIFTY_MOVE_RULE,
+    /// automatic after 75 moves by each side without a capture or pawn move
+    SEVENTY_FIVE_MOVE_RULE,
+    /// claimed when the same position has occurred three times
+    THREEFOLD_REPETITION,
+    /// automatic when the same position has occurred five times
+    FIVEFOLD_REPETITION,
+    /// automatic when neither side has enough pieces left to checkmate
+    INSUFFICIENT_MATERIAL,
+}
+
+impl GameStatus {
+    /// checks if the game has finished
+    pub fn is_over(&self) -> bool {
+        *self != GameStatus::ONGOING
+    }
+}
+
+impl Display for GameStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameStatus::ONGOING => write!(f, "Game in progress"),
+            GameStatus::CHECKMATE(PieceColour::WHITE) => write!(f, "White wins by checkmate"),
+            GameStatus::CHECKMATE(PieceColour::BLACK) => write!(f, "Black wins by checkmate"),
+            GameStatus::STALEMATE => write!(f, "Draw by stalemate"),
+            GameStatus::FIFTY_MOVE_RULE => write!(f, "Draw by the fifty move rule"),
+            GameStatus::SEVENTY_FIVE_MOVE_RULE => write!(f, "Draw by the seventy-five move rule"),
+            GameStatus::THREEFOLD_REPETITION => write!(f, "Draw by threefold repetition"),
+            GameStatus::FIVEFOLD_REPETITION => write!(f, "Draw by fivefold repetition"),
+            GameStatus::INSUFFICIENT_MATERIAL => write!(f, "Draw by insufficient material"),
+        }
+    }
+}
 
 pub struct Game<T: IO> {
     pub board: Board,
     pub io: T,
+    pub status: GameStatus,
+    /// hashes of every position reached so far, including the current one
+    pub history: Vec<u64>,
+    /// moves played so far, with what is needed to take each one back
+    pub moves: Vec<(Move, UndoInfo)>,
+    /// what was written about each move in moves, eg. in a loaded pgn
+    pub annotations: Vec<Annotation>,
+    /// pgn comments before the first move
+    pub comments: Vec<String>,
+    /// pgn tag pairs, eg. the names of the players
+    pub tags: Vec<(String, String)>,
+    /// the colour the engine plays, if any
+    pub engine_colour: Option<PieceColour>,
+    /// how long the engine thinks for each move
+    pub engine_limits: SearchLimits,
+    /// kept between moves, so the engine can reuse what it found.
+    /// only made once the engine first searches
+    pub engine_table: Option<TranspositionTable>,
+    /// the size of the engine's table
+    pub engine_table_megabytes: usize,
     is_running: bool,
 }
 
 impl<T: IO> Game<T> {
     pub fn new(io: T) -> Result<Self, String> {
-        Ok(Self {
-            board: Board::from_fen(STARTING_FEN)?,
-            io: io,
+        let board = Board::from_fen(STARTING_FEN)?;
+        let mut game = Self {
+            history: Vec::new(),
+            moves: Vec::new(),
+            annotations: Vec::new(),
+            comments: Vec::new(),
+            tags: Vec::new(),
+            engine_colour: None,
+            engine_limits: SearchLimits::time(Duration::from_secs(1)),
+            engine_table: None,
+            engine_table_megabytes: TranspositionTable::DEFAULT_MEGABYTES,
+            board,
+            io,
+            status: GameStatus::ONGOING,
             is_running: true,
-        })
+        };
+        game.reset(board);
+        Ok(game)
+    }
+
+    /// starts over from the given position, forgetting all moves
+    pub fn reset(&mut self, board: Board) {
+        self.board = board;
+        self.history = vec![board.hash];
+        self.moves.clear();
+        self.annotations.clear();
+        self.comments.clear();
+        self.tags.clear();
+        self.update_status();
+    }
+
+    /// reads a game from pgn and replays its moves
+    pub fn from_pgn(io: T, pgn: &str) -> Result<Self, String> {
+        let mut game = Self::new(io)?;
+        game.load_pgn(pgn)?;
+        Ok(game)
+    }
+
+    /// replaces the current game with the first game in a pgn file.
+    /// the current game is kept if the pgn can't be read
+    pub fn load_pgn(&mut self, pgn: &str) -> Result<(), PgnError> {
+        let pgn = Pgn::parse(pgn)?;
+        let start = Board::from_fen(pgn.tag("FEN").unwrap_or(STARTING_FEN))
+            .map_err(PgnError::INVALID_FEN)?;
+
+        // check every move before changing anything
+        let mut board = start;
+        let mut moves = Vec::new();
+        for (ply, pgn_move) in pgn.line.moves.iter().enumerate() {
+            let mv = board
+                .parse_san(&pgn_move.san)
+                .map_err(|error| PgnError::INVALID_MOVE {
+                    ply: ply + 1,
+                    san: pgn_move.san.clone(),
+                    error,
+                })?;
+            board.make(mv);
+            moves.push(mv);
+        }
+
+        self.reset(start);
+        for mv in moves {
+            self.play(mv);
+        }
+        self.annotations = pgn.line.moves.into_iter().map(|mv| mv.annotation).collect();
+        self.comments = pgn.line.comments;
+        self.tags = pgn.tags;
+        // without a Result tag, keep the result written after the moves
+        if let (None, Some(result)) = (self.tag("Result"), pgn.result) {
+            self.tags.push(("Result".to_owned(), result));
+        }
+        Ok(())
+    }
+
+    /// writes the game as pgn, with the seven tag roster first
+    pub fn to_pgn(&self) -> String {
+        let result = self.result();
+        let start = self.start_board();
+        let start_fen = start.to_fen();
+
+        let mut tags: Vec<(&str, String)> = SEVEN_TAG_ROSTER
+            .iter()
+            .map(|&name| {
+                // unknown values are written as question marks
+                let unknown = if name == "Date" { "????.??.??" } else { "?" };
+                let value = match name {
+                    "Result" => result,
+                    _ => self.tag(name).unwrap_or(unknown),
+                };
+                (name, value.to_owned())
+            })
+            .collect();
+        if start_fen != STARTING_FEN {
+            tags.push(("SetUp", "1".to_owned()));
+            tags.push(("FEN", start_fen));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.contains(&name.as_str()) && name != "SetUp" && name != "FEN" {
+                tags.push((name, value.clone()));
+            }
+        }
+
+        let mut pgn = String::new();
+        for (name, value) in tags {
+            pgn.push_str(&format!("[{} {}]\n", name, pgn::quote_tag(&value)));
+        }
+        pgn.push('\n');
+        let movetext = self
+            .pgn_line(true)
+            .to_movetext(start.fullmove_number, start.turn);
+        pgn.push_str(&pgn::wrap_movetext(&format!("{} {}", movetext, result)));
+        pgn.push('\n');
+        pgn
+    }
+
+    /// gets the value of a pgn tag
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// the pgn result, eg. "1-0". an unfinished game keeps the result it was loaded with
+    pub fn result(&self) -> &str {
+        match self.status {
+            GameStatus::ONGOING => self.tag("Result").unwrap_or("*"),
+            GameStatus::CHECKMATE(PieceColour::WHITE) => "1-0",
+            GameStatus::CHECKMATE(PieceColour::BLACK) => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+
+    /// the position before the first move
+    pub fn start_board(&self) -> Board {
+        let mut board = self.board;
+        for &(mv, undo) in self.moves.iter().rev() {
+            board.unmake(mv, undo);
+        }
+        board
+    }
+
+    /// writes the moves played so far in standard algebraic notation, eg. "1. e4 e5 2. Nf3"
+    pub fn san_history(&self) -> String {
+        let start = self.start_board();
+        self.pgn_line(false)
+            .to_movetext(start.fullmove_number, start.turn)
     }
 
+    /// the moves played so far as a pgn line, with or without their annotations
+    fn pgn_line(&self, annotated: bool) -> PgnLine {
+        let mut board = self.start_board();
+        let mut line = PgnLine::default();
+        if annotated {
+            line.comments = self.comments.clone();
+        }
+        for (ply, &(mv, _)) in self.moves.iter().enumerate() {
+            let annotation = match self.annotations.get(ply) {
+                Some(annotation) if annotated => annotation.clone(),
+                _ => Annotation::default(),
+            };
+            line.moves.push(PgnMove {
+                san: mv.to_san(&board),
+                annotation,
+            });
+            board.make(mv);
+        }
+        line
+    }
+
+    /// counts how many times the current position has occurred
+    pub fn repetitions(&self) -> usize {
+        // positions before the last capture or pawn move can't be repeated
+        self.history
+            .iter()
+            .rev()
+            .take(self.board.halfmove_clock as usize + 1)
+            .filter(|&&hash| hash == self.board.hash)
+            .count()
+    }
+
+    /// makes a legal move and checks if it ended the game
+    pub fn play(&mut self, mv: Move) {
+        let undo = self.board.make(mv);
+        self.moves.push((mv, undo));
+        self.annotations.push(Annotation::default());
+        self.history.push(self.board.hash);
+        self.update_status();
+    }
+
+    /// takes back the last move, returning false if there are none
+    pub fn undo(&mut self) -> bool {
+        match self.moves.pop() {
+            Some((mv, undo)) => {
+                self.board.unmake(mv, undo);
+                self.annotations.pop();
+                self.history.pop();
+                self.update_status();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// checks if the current position ends the game
+    fn update_status(&mut self) {
+        self.status = self.board.status();
+        if self.status == GameStatus::ONGOING && self.repetitions() >= 5 {
+            self.status = GameStatus::FIVEFOLD_REPETITION;
+        }
+    }
+
+    /// reads and carries out commands until the player quits. a finished game stays open,
+    /// so it can still be saved, undone or replaced with a new one
     pub fn run(&mut self) -> UnitResult {
+        if self.status.is_over() {
+            self.show_result()?;
+        }
+
         while self.is_running {
             self.render()?;
             self.update()?;
@@ -26,18 +306,510 @@ impl<T: IO> Game<T> {
         Ok(())
     }
 
+    /// the move that led to the current position, if any
+    pub fn last_move(&self) -> Option<Move> {
+        self.moves.last().map(|&(mv, _)| mv)
+    }
+
     fn render(&mut self) -> UnitResult {
+        self.io.set_last_move(self.last_move());
         self.io.render(&self.board)
     }
 
+    fn show_result(&mut self) -> UnitResult {
+        self.io.set_last_move(self.last_move());
+        self.io.show_result(&self.board, self.status)
+    }
+
+    /// carries out one command, then shows the result if it ended the game
+    /// or hides it if the game carries on, eg. after an undo
     fn update(&mut self) -> UnitResult {
+        let before = (self.status, self.board.hash);
+        self.handle_command()?;
+
+        if (self.status, self.board.hash) != before {
+            if self.status.is_over() {
+                self.show_result()?;
+            } else if before.0.is_over() {
+                self.io.hide_result();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// has the engine play a move for the side to move
+    fn engine_move(&mut self) {
+        if self.status.is_over() {
+            println!("Cannot play a move: the game is over");
+            return;
+        }
+
+        let result = engine::search_with_progress(
+            &self.board,
+            self.engine_limits,
+            self.engine_table
+                .get_or_insert_with(|| TranspositionTable::new(self.engine_table_megabytes)),
+            // every position before the current one
+            &self.history[..self.history.len() - 1],
+            |_| (),
+        );
+        if let Some(mv) = result.best_move {
+            println!(
+                "Engine plays {} ({})",
+                mv.to_san(&self.board),
+                result.score_string()
+            );
+            self.play(mv);
+        }
+    }
+
+    fn handle_command(&mut self) -> UnitResult {
+        if self.engine_colour == Some(self.board.turn) && !self.status.is_over() {
+            self.engine_move();
+            return Ok(());
+        }
+
         match self.io.get_command(&self.board)? {
             Command::None => Ok(()),
             Command::Quit => {
                 self.is_running = false;
                 Ok(())
             }
-            cmd => self.board.update(cmd),
+            Command::ClaimDraw => {
+                if self.status.is_over() {
+                    println!("Cannot claim draw: the game is over");
+                } else if self.board.can_claim_fifty_move_rule() {
+                    self.status = GameStatus::FIFTY_MOVE_RULE;
+                } else if self.repetitions() >= 3 {
+                    self.status = GameStatus::THREEFOLD_REPETITION;
+                } else {
+                    println!("Cannot claim draw: no draw to claim");
+                }
+                Ok(())
+            }
+            Command::Undo => {
+                if !self.undo() {
+                    println!("Cannot undo: no moves have been made");
+                }
+                Ok(())
+            }
+            Command::New(board) => {
+                self.reset(*board);
+                Ok(())
+            }
+            Command::Fen => {
+                println!("{}", self.board);
+                Ok(())
+            }
+            Command::Flip => {
+                self.io.flip();
+                Ok(())
+            }
+            Command::Moves => {
+                let mut moves: Vec<String> = self
+                    .board
+                    .legal_moves()
+                    .into_iter()
+                    .map(|mv| mv.to_san(&self.board))
+                    .collect();
+                moves.sort();
+                println!("{}", moves.join(" "));
+                Ok(())
+            }
+            Command::History => {
+                println!("{}", self.san_history());
+                Ok(())
+            }
+            Command::Pgn => {
+                print!("{}", self.to_pgn());
+                Ok(())
+            }
+            Command::Save(path) => {
+                match fs::write(&path, self.to_pgn()) {
+                    Ok(()) => println!("Saved game to {}", path),
+                    Err(err) => println!("Cannot save game: {}", err),
+                }
+                Ok(())
+            }
+            Command::Load(path) => {
+                match fs::read_to_string(&path) {
+                    Ok(pgn) => {
+                        if let Err(err) = self.load_pgn(&pgn) {
+                            println!("{}", err);
+                        }
+                    }
+                    Err(err) => println!("Cannot load game: {}", err),
+                }
+                Ok(())
+            }
+            Command::Go => {
+                self.engine_move();
+                Ok(())
+            }
+            Command::Analyse => {
+                let board = self.board;
+                engine::search_with_progress(
+                    &board,
+                    self.engine_limits,
+                    self.engine_table.get_or_insert_with(|| {
+                        TranspositionTable::new(self.engine_table_megabytes)
+                    }),
+                    // every position before the current one
+                    &self.history[..self.history.len() - 1],
+                    |result| {
+                        println!(
+                            "depth {} score {} nodes {} hashfull {} pv {}",
+                            result.depth,
+                            result.score_string(),
+                            result.nodes,
+                            result.hashfull,
+                            result.pv_san(&board)
+                        )
+                    },
+                );
+                Ok(())
+            }
+            Command::Eval => {
+                println!("{}", engine::evaluate_breakdown(&self.board));
+                Ok(())
+            }
+            Command::Engine(colour) => {
+                self.engine_colour = colour;
+                Ok(())
+            }
+            Command::Help => {
+                println!("{}", HELP);
+                Ok(())
+            }
+            Command::Move(_) if self.status.is_over() => {
+                println!("Cannot make move: the game is over");
+                Ok(())
+            }
+            Command::Move(mv) => {
+                match self.board.find_move(mv) {
+                    Some(mv) => self.play(mv),
+                    None => println!("Cannot make move: invalid move"),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::coordinate_to_index;
+    use crate::san::SanError;
+
+    /// plays a fixed list of commands, then quits
+    struct ScriptedIO {
+        commands: Vec<Command>,
+        /// every result shown, in order
+        results: Vec<GameStatus>,
+    }
+
+    impl ScriptedIO {
+        fn new(mut commands: Vec<Command>) -> Self {
+            commands.reverse();
+            Self {
+                commands,
+                results: Vec::new(),
+            }
+        }
+    }
+
+    impl IO for ScriptedIO {
+        fn get_command(&mut self, board: &Board) -> Result<Command, String> {
+            Ok(self.commands.pop().unwrap_or(Command::Quit))
+        }
+        fn render(&mut self, board: &Board) -> UnitResult {
+            Ok(())
+        }
+        fn show_result(&mut self, board: &Board, status: GameStatus) -> UnitResult {
+            self.results.push(status);
+            Ok(())
+        }
+        fn hide_result(&mut self) {
+            self.results.push(GameStatus::ONGOING);
+        }
+    }
+
+    fn mv(start_file: u8, start_rank: u8, end_file: u8, end_rank: u8) -> Command {
+        Command::Move(Move::from_squares(
+            coordinate_to_index(start_file, start_rank),
+            coordinate_to_index(end_file, end_rank),
+            None,
+        ))
+    }
+
+    /// both sides move a knight out and back
+    fn knight_shuffle(times: usize) -> Vec<Command> {
+        let mut commands = Vec::new();
+        for _ in 0..times {
+            commands.push(mv(6, 7, 5, 5));
+            commands.push(mv(6, 0, 5, 2));
+            commands.push(mv(5, 5, 6, 7));
+            commands.push(mv(5, 2, 6, 0));
         }
+        commands
+    }
+
+    #[test]
+    fn threefold_repetition_claim() {
+        let mut commands = knight_shuffle(2);
+        commands.push(Command::ClaimDraw);
+        let mut game = Game::new(ScriptedIO::new(commands)).unwrap();
+        game.run().unwrap();
+        assert_eq!(game.status, GameStatus::THREEFOLD_REPETITION)
+    }
+    #[test]
+    fn early_claim_is_refused() {
+        let mut commands = knight_shuffle(1);
+        commands.push(Command::ClaimDraw);
+        let mut game = Game::new(ScriptedIO::new(commands)).unwrap();
+        game.run().unwrap();
+        assert_eq!(game.status, GameStatus::ONGOING)
+    }
+    #[test]
+    fn repetition_after_double_push() {
+        // the en passant square after e4 doesn't count, since no black pawn can use it
+        // 1. e4 Nc6 2. Nf3 Nb8 3. Ng1 Nc6 4. Nf3 Nb8 5. Ng1
+        let mut commands = vec![mv(4, 6, 4, 4)];
+        for _ in 0..2 {
+            commands.push(mv(1, 0, 2, 2));
+            commands.push(mv(6, 7, 5, 5));
+            commands.push(mv(2, 2, 1, 0));
+            commands.push(mv(5, 5, 6, 7));
+        }
+        commands.push(Command::ClaimDraw);
+        let mut game = Game::new(ScriptedIO::new(commands)).unwrap();
+        game.run().unwrap();
+        assert_eq!(game.status, GameStatus::THREEFOLD_REPETITION)
+    }
+    #[test]
+    fn fivefold_repetition() {
+        let mut game = Game::new(ScriptedIO::new(knight_shuffle(4))).unwrap();
+        game.run().unwrap();
+        assert_eq!(game.status, GameStatus::FIVEFOLD_REPETITION);
+        assert_eq!(game.history.len(), 17)
+    }
+    #[test]
+    fn undo() {
+        let mut commands = knight_shuffle(1);
+        commands.push(Command::Undo);
+        commands.push(Command::Undo);
+        let mut game = Game::new(ScriptedIO::new(commands)).unwrap();
+        game.run().unwrap();
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(game.history.len(), 3);
+        assert_eq!(
+            game.board.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 2 2"
+        )
+    }
+    #[test]
+    fn undo_recomputes_status() {
+        // a loaded game can go on past the seventy-five move rule, and
+        // taking back the last move returns to the finished position
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 120").unwrap();
+        let mut game = Game::new(ScriptedIO::new(Vec::new())).unwrap();
+        game.reset(board);
+        for san in ["Ra2", "Kd7"] {
+            game.play(game.board.parse_san(san).unwrap());
+        }
+        assert!(game.undo());
+        assert_eq!(game.status, GameStatus::SEVENTY_FIVE_MOVE_RULE);
+        assert!(game.undo());
+        assert_eq!(game.status, GameStatus::ONGOING)
+    }
+    #[test]
+    fn finished_game_stays_open() {
+        let path = std::env::temp_dir().join(format!("chess-finished-{}.pgn", std::process::id()));
+        // 1. f3 e5 2. g4 Qh4#
+        let mut commands = vec![
+            mv(5, 6, 5, 5),
+            mv(4, 1, 4, 3),
+            mv(6, 6, 6, 4),
+            mv(3, 0, 7, 4),
+        ];
+        commands.push(Command::Save(path.to_string_lossy().into_owned()));
+        // moves and draw claims are refused once the game is over
+        commands.push(mv(4, 6, 4, 4));
+        commands.push(Command::ClaimDraw);
+        let mut game = Game::new(ScriptedIO::new(commands)).unwrap();
+        game.run().unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(saved.ends_with("1. f3 e5 2. g4 Qh4# 0-1\n"));
+        assert_eq!(game.moves.len(), 4);
+        assert_eq!(game.status, GameStatus::CHECKMATE(PieceColour::BLACK));
+        assert_eq!(game.io.results, [GameStatus::CHECKMATE(PieceColour::BLACK)]);
+
+        // taking back the mate carries the game on
+        let mut game = Game::new(ScriptedIO::new(vec![Command::Undo, mv(4, 3, 4, 4)])).unwrap();
+        for san in ["f3", "e5", "g4", "Qh4"] {
+            game.play(game.board.parse_san(san).unwrap());
+        }
+        game.run().unwrap();
+        assert_eq!(game.status, GameStatus::ONGOING);
+        assert_eq!(game.san_history(), "1. f3 e5 2. g4 e4");
+        assert_eq!(
+            game.io.results,
+            [
+                GameStatus::CHECKMATE(PieceColour::BLACK),
+                GameStatus::ONGOING
+            ]
+        )
+    }
+    #[test]
+    fn new_game() {
+        let mut commands = knight_shuffle(1);
+        let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        commands.push(Command::New(Box::new(board)));
+        let mut game = Game::new(ScriptedIO::new(commands)).unwrap();
+        game.run().unwrap();
+        assert_eq!(game.board, board);
+        assert!(game.moves.is_empty());
+        assert_eq!(game.history, vec![board.hash])
+    }
+    #[test]
+    fn new_game_from_finished_position() {
+        // the result is shown, and commands are still read afterwards
+        let finished = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let start = Board::from_fen(STARTING_FEN).unwrap();
+        let commands = vec![
+            Command::New(Box::new(finished)),
+            Command::New(Box::new(start)),
+            mv(4, 6, 4, 4),
+        ];
+        let mut game = Game::new(ScriptedIO::new(commands)).unwrap();
+        game.run().unwrap();
+        assert_eq!(
+            game.io.results,
+            [GameStatus::INSUFFICIENT_MATERIAL, GameStatus::ONGOING]
+        );
+        assert_eq!(game.san_history(), "1. e4")
+    }
+    #[test]
+    fn san_history() {
+        let mut game = Game::new(ScriptedIO::new(knight_shuffle(1))).unwrap();
+        game.run().unwrap();
+        assert_eq!(game.san_history(), "1. Nf3 Nf6 2. Ng1 Ng8");
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        game.reset(board);
+        game.play(game.board.parse_san("Kd7").unwrap());
+        game.play(game.board.parse_san("e4").unwrap());
+        assert_eq!(game.san_history(), "1... Kd7 2. e4")
+    }
+    #[test]
+    fn pgn_round_trip() {
+        let pgn = r#"[Event "Club championship"]
+[White "Anderssen"]
+[Black "Kieseritzky"]
+[Opening "Scholar's mate"]
+
+1. e4 e5 2. Bc4 {aiming at f7} Nc6 3. Qh5 Nf6?? (3... g6) 4. Qxf7# 1-0"#;
+        let game = Game::from_pgn(ScriptedIO::new(Vec::new()), pgn).unwrap();
+        assert_eq!(game.status, GameStatus::CHECKMATE(PieceColour::WHITE));
+        assert_eq!(
+            game.to_pgn(),
+            r#"[Event "Club championship"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "Anderssen"]
+[Black "Kieseritzky"]
+[Result "1-0"]
+[Opening "Scholar's mate"]
+
+1. e4 e5 2. Bc4 {aiming at f7} 2... Nc6 3. Qh5 Nf6?? (3... g6) 4. Qxf7# 1-0
+"#
+        );
+
+        let copy = Game::from_pgn(ScriptedIO::new(Vec::new()), &game.to_pgn()).unwrap();
+        assert_eq!(copy.board, game.board);
+        assert_eq!(copy.to_pgn(), game.to_pgn())
+    }
+    #[test]
+    fn pgn_from_position() {
+        let mut game = Game::new(ScriptedIO::new(Vec::new())).unwrap();
+        game.load_pgn("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n1... Kd7 2. e4 *")
+            .unwrap();
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(game.result(), "*");
+        assert!(game.to_pgn().ends_with(
+            "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n\n1... Kd7 2. e4 *\n"
+        ))
+    }
+    #[test]
+    fn pgn_result_without_tag() {
+        // the result after the moves is kept when there is no Result tag
+        let mut game = Game::new(ScriptedIO::new(Vec::new())).unwrap();
+        game.load_pgn("1. e4 e5 2. Nf3 1-0").unwrap();
+        assert_eq!(game.result(), "1-0");
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[Result \"1-0\"]"));
+        assert!(pgn.ends_with("2. Nf3 1-0\n"))
+    }
+    #[test]
+    fn pgn_with_illegal_move() {
+        let mut game = Game::new(ScriptedIO::new(knight_shuffle(1))).unwrap();
+        game.run().unwrap();
+        assert_eq!(
+            game.load_pgn("1. e4 e5 2. Ke3"),
+            Err(PgnError::INVALID_MOVE {
+                ply: 3,
+                san: "Ke3".to_owned(),
+                error: SanError::ILLEGAL_MOVE
+            })
+        );
+        // the game is unchanged
+        assert_eq!(game.moves.len(), 4)
+    }
+    #[test]
+    fn engine_draws_by_repetition() {
+        // a queen down, the engine goes back to a position that has come up twice
+        let board = Board::from_fen("7k/8/8/8/8/8/8/KQ6 b - - 0 1").unwrap();
+        let mut game = Game::new(ScriptedIO::new(vec![Command::Engine(Some(
+            PieceColour::BLACK,
+        ))]))
+        .unwrap();
+        game.reset(board);
+        for san in ["Kg8", "Qc1", "Kh8", "Qb1"].repeat(2) {
+            game.play(game.board.parse_san(san).unwrap());
+        }
+        game.engine_limits = SearchLimits::depth(3);
+        game.run().unwrap();
+        assert!(game.san_history().ends_with("5. Qb1 Kg8"));
+        assert_eq!(game.repetitions(), 3)
+    }
+    #[test]
+    fn engine_table_made_on_first_search() {
+        let mut game = Game::new(ScriptedIO::new(vec![Command::Go])).unwrap();
+        assert!(game.engine_table.is_none());
+
+        game.engine_table_megabytes = 1;
+        game.engine_limits = SearchLimits::depth(1);
+        game.run().unwrap();
+        let table = game.engine_table.as_ref().unwrap();
+        assert_eq!(table.len(), TranspositionTable::new(1).len())
+    }
+    #[test]
+    fn engine_opponent() {
+        // the engine plays white and finds the back rank mate
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let commands = vec![
+            Command::New(Box::new(board)),
+            Command::Engine(Some(PieceColour::WHITE)),
+        ];
+        let mut game = Game::new(ScriptedIO::new(commands)).unwrap();
+        game.engine_limits = SearchLimits::depth(2);
+        game.run().unwrap();
+        assert_eq!(game.status, GameStatus::CHECKMATE(PieceColour::WHITE));
+        assert_eq!(game.san_history(), "1. Ra8#")
     }
 }
@@ -1,74 +1,306 @@
-use std::io::{self, stdin, Write};
+use std::fmt::Write as _;
+use std::io::{self, stdin, BufRead, IsTerminal, Write};
 
 use crate::{
     board::Board,
-    board::{coordinate_to_index, Square},
+    board::{coordinate_to_index, piece_to_char, Square},
+    game::GameStatus,
     io::{Command, IO},
+    moves::Move,
     PieceColour, PieceType, UnitResult,
 };
 
+/// how the console draws the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConsoleStyle {
+    /// chess symbols, eg. ♔, instead of letters
+    pub unicode: bool,
+    /// ansi background colours for the squares, the last move and a king in check
+    pub colour: bool,
+    /// whether black's side of the board is shown at the bottom
+    pub flipped: bool,
+}
+
+const RESET: &str = "\x1b[0m";
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const DARK_SQUARE: &str = "\x1b[48;5;137m";
+const LIGHT_LAST_MOVE: &str = "\x1b[48;5;186m";
+const DARK_LAST_MOVE: &str = "\x1b[48;5;143m";
+const CHECK: &str = "\x1b[48;5;167m";
+const WHITE_PIECE: &str = "\x1b[97m";
+const BLACK_PIECE: &str = "\x1b[30m";
+
 /// Renders the game through a text cli
-pub struct ConsoleRenderer;
+pub struct ConsoleRenderer {
+    style: ConsoleStyle,
+    last_move: Option<Move>,
+    input: Box<dyn BufRead>,
+    /// whether input is typed by a person, rather than piped in from a file or another program
+    interactive: bool,
+}
 
 impl ConsoleRenderer {
-    pub fn new() -> Result<Self, String> {
-        Ok(Self {})
+    /// reads commands from stdin
+    pub fn new(style: ConsoleStyle) -> Result<Self, String> {
+        Ok(Self {
+            style,
+            last_move: None,
+            input: Box::new(stdin().lock()),
+            interactive: stdin().is_terminal(),
+        })
+    }
+
+    /// reads commands from the given input, one per line, without prompting
+    pub fn from_reader(input: impl BufRead + 'static, style: ConsoleStyle) -> Self {
+        Self {
+            style,
+            last_move: None,
+            input: Box::new(input),
+            interactive: false,
+        }
     }
 
     fn piece_to_char(&self, piece_option: &Square) -> char {
-        match piece_option {
-            Some(piece) => match (piece.colour(), piece.piece_type()) {
-                (PieceColour::WHITE, PieceType::KING) => 'K',
-                (PieceColour::WHITE, PieceType::QUEEN) => 'Q',
-                (PieceColour::WHITE, PieceType::ROOK) => 'R',
-                (PieceColour::WHITE, PieceType::BISHOP) => 'B',
-                (PieceColour::WHITE, PieceType::KNIGHT) => 'N',
-                (PieceColour::WHITE, PieceType::PAWN) => 'P',
-                (PieceColour::BLACK, PieceType::KING) => 'k',
-                (PieceColour::BLACK, PieceType::QUEEN) => 'q',
-                (PieceColour::BLACK, PieceType::ROOK) => 'r',
-                (PieceColour::BLACK, PieceType::BISHOP) => 'b',
-                (PieceColour::BLACK, PieceType::KNIGHT) => 'n',
-                (PieceColour::BLACK, PieceType::PAWN) => 'p',
-            },
-            None => ' ',
+        let piece = match piece_option {
+            Some(piece) => piece,
+            None => return ' ',
+        };
+        if !self.style.unicode {
+            return piece_to_char(piece.colour(), piece.piece_type());
+        }
+
+        // with colour on, both sides use the solid symbols and are told apart by colour
+        let solid = self.style.colour || piece.colour() == PieceColour::BLACK;
+        match (piece.piece_type(), solid) {
+            (PieceType::KING, false) => '♔',
+            (PieceType::QUEEN, false) => '♕',
+            (PieceType::ROOK, false) => '♖',
+            (PieceType::BISHOP, false) => '♗',
+            (PieceType::KNIGHT, false) => '♘',
+            (PieceType::PAWN, false) => '♙',
+            (PieceType::KING, true) => '♚',
+            (PieceType::QUEEN, true) => '♛',
+            (PieceType::ROOK, true) => '♜',
+            (PieceType::BISHOP, true) => '♝',
+            (PieceType::KNIGHT, true) => '♞',
+            (PieceType::PAWN, true) => '♟',
+        }
+    }
+
+    /// the ansi colours of a square and the piece on it
+    fn square_colours(&self, board: &Board, file: u8, rank: u8) -> String {
+        let index = coordinate_to_index(file, rank);
+        let is_light = (file + rank).is_multiple_of(2);
+        let in_last_move = self
+            .last_move
+            .is_some_and(|mv| mv.from() == index || mv.to() == index);
+        let in_check =
+            board.is_in_check(board.turn) && board.king_square(board.turn) == Some((file, rank));
+
+        let background = match (in_check, in_last_move, is_light) {
+            (true, _, _) => CHECK,
+            (false, true, true) => LIGHT_LAST_MOVE,
+            (false, true, false) => DARK_LAST_MOVE,
+            (false, false, true) => LIGHT_SQUARE,
+            (false, false, false) => DARK_SQUARE,
+        };
+        let foreground = match board.squares[index].map(|piece| piece.colour()) {
+            Some(PieceColour::BLACK) => BLACK_PIECE,
+            _ => WHITE_PIECE,
+        };
+        format!("{}{}", background, foreground)
+    }
+
+    /// draws the board and whose turn it is
+    fn draw(&self, board: &Board) -> String {
+        // ranks and files in the order they are shown
+        let order: Vec<u8> = if self.style.flipped {
+            (0..8).rev().collect()
+        } else {
+            (0..8).collect()
+        };
+        let mut out = String::new();
+
+        if self.style.colour {
+            for &rank in &order {
+                write!(out, "{} ", 8 - rank).unwrap();
+                for &file in &order {
+                    let piece = &board.squares[coordinate_to_index(file, rank)];
+                    write!(
+                        out,
+                        "{} {} ",
+                        self.square_colours(board, file, rank),
+                        self.piece_to_char(piece)
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "{}", RESET).unwrap();
+            }
+            out.push_str("  ");
+            for &file in &order {
+                write!(out, " {} ", (b'a' + file) as char).unwrap();
+            }
+            out.push('\n');
+        } else {
+            out.push_str("  ");
+            for &file in &order {
+                write!(out, "  {} ", (b'a' + file) as char).unwrap();
+            }
+            // the header has always ended in two spaces
+            out.push_str(" \n");
+            out.push_str("  +---+---+---+---+---+---+---+---+\n");
+            for &rank in &order {
+                write!(out, "{} ", 8 - rank).unwrap();
+                for &file in &order {
+                    let piece = &board.squares[coordinate_to_index(file, rank)];
+                    write!(out, "| {} ", self.piece_to_char(piece)).unwrap();
+                }
+                out.push_str("|\n");
+                out.push_str("  +---+---+---+---+---+---+---+---+\n");
+            }
+        }
+
+        if board.is_in_check(board.turn) {
+            writeln!(out, "{:?}'s turn (check)", &board.turn).unwrap();
+        } else {
+            writeln!(out, "{:?}'s turn", &board.turn).unwrap();
         }
+
+        out
     }
 }
 
 impl IO for ConsoleRenderer {
     fn get_command(&mut self, board: &Board) -> Result<Command, String> {
-        print!(">> ");
-        io::stdout().flush().map_err(|err| err.to_string())?;
-        let mut buffer = String::new();
+        if self.interactive {
+            print!(">> ");
+            io::stdout().flush().map_err(|err| err.to_string())?;
+        }
 
-        stdin()
+        let mut buffer = String::new();
+        let read = self
+            .input
             .read_line(&mut buffer)
             .map_err(|err| err.to_string())?;
+        // the input has ended, eg. ctrl-d or the end of a piped file
+        if read == 0 {
+            return Ok(Command::Quit);
+        }
 
-        // todo make lower
-        let cmd: Vec<&str> = buffer.strip_suffix("\r\n").unwrap().split(" ").collect();
+        // lines can end in "\n" or "\r\n"
+        let line = buffer.trim_end_matches(['\r', '\n']);
 
-        Ok(Command::from(cmd))
+        match Command::parse(line, board) {
+            Ok(command) => Ok(command),
+            Err(err) => {
+                println!("{}", err);
+                Ok(Command::None)
+            }
+        }
     }
 
     fn render(&mut self, board: &Board) -> UnitResult {
-        println!("    a   b   c   d   e   f   g   h  ");
-        println!("  +---+---+---+---+---+---+---+---+");
-        for rank in 0..8 {
-            print!("{} ", 8 - rank);
-            for file in 0..8 {
-                print!(
-                    "| {} ",
-                    self.piece_to_char(&board.squares[coordinate_to_index(file, rank)])
-                );
-            }
-            println!("|");
-            println!("  +---+---+---+---+---+---+---+---+");
-        }
+        print!("{}", self.draw(board));
+
+        Ok(())
+    }
 
-        println!("{:?}'s turn", &board.turn);
+    fn flip(&mut self) {
+        self.style.flipped = !self.style.flipped;
+    }
+
+    fn set_last_move(&mut self, mv: Option<Move>) {
+        self.last_move = mv;
+    }
+
+    fn show_result(&mut self, board: &Board, status: GameStatus) -> UnitResult {
+        self.render(board)?;
+        println!("{}", status);
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{game::Game, STARTING_FEN};
+    use std::io::Cursor;
+
+    #[test]
+    fn line_endings() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        let mut console = ConsoleRenderer::from_reader(
+            Cursor::new("e2e4\r\nundo\nquit"),
+            ConsoleStyle::default(),
+        );
+        assert!(matches!(console.get_command(&board), Ok(Command::Move(_))));
+        assert!(matches!(console.get_command(&board), Ok(Command::Undo)));
+        assert!(matches!(console.get_command(&board), Ok(Command::Quit)))
+    }
+    #[test]
+    fn end_of_input() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        let mut console =
+            ConsoleRenderer::from_reader(Cursor::new("\nhello\n"), ConsoleStyle::default());
+        assert!(matches!(console.get_command(&board), Ok(Command::None)));
+        assert!(matches!(console.get_command(&board), Ok(Command::None)));
+        assert!(matches!(console.get_command(&board), Ok(Command::Quit)));
+        assert!(matches!(console.get_command(&board), Ok(Command::Quit)))
+    }
+    #[test]
+    fn scripted_game() {
+        let input = "f3\ne5\ng4\nQh4\n";
+        let console = ConsoleRenderer::from_reader(Cursor::new(input), ConsoleStyle::default());
+        let mut game = Game::new(console).unwrap();
+        game.run().unwrap();
+        assert_eq!(game.status, GameStatus::CHECKMATE(PieceColour::BLACK))
+    }
+    #[test]
+    fn plain_board() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        let console = ConsoleRenderer::from_reader(Cursor::new(""), ConsoleStyle::default());
+        let lines: Vec<String> = console.draw(&board).lines().map(String::from).collect();
+        assert_eq!(lines[0], "    a   b   c   d   e   f   g   h  ");
+        assert_eq!(lines[2], "8 | r | n | b | q | k | b | n | r |");
+        assert_eq!(lines[16], "1 | R | N | B | Q | K | B | N | R |");
+        assert_eq!(lines[18], "WHITE's turn")
+    }
+    #[test]
+    fn unicode_and_flipped() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        let style = ConsoleStyle {
+            unicode: true,
+            flipped: true,
+            ..ConsoleStyle::default()
+        };
+        let console = ConsoleRenderer::from_reader(Cursor::new(""), style);
+        let lines: Vec<String> = console.draw(&board).lines().map(String::from).collect();
+        assert_eq!(lines[0], "    h   g   f   e   d   c   b   a  ");
+        assert_eq!(lines[2], "1 | ♖ | ♘ | ♗ | ♔ | ♕ | ♗ | ♘ | ♖ |");
+        assert_eq!(lines[16], "8 | ♜ | ♞ | ♝ | ♚ | ♛ | ♝ | ♞ | ♜ |")
+    }
+    #[test]
+    fn colour_highlights() {
+        // after 1. f3 e5 2. g4 Qh4#
+        let board =
+            Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        let style = ConsoleStyle {
+            colour: true,
+            ..ConsoleStyle::default()
+        };
+        let mut console = ConsoleRenderer::from_reader(Cursor::new(""), style);
+        console.set_last_move(Some(Move::from_squares(
+            coordinate_to_index(3, 0),
+            coordinate_to_index(7, 4),
+            None,
+        )));
+        let lines: Vec<String> = console.draw(&board).lines().map(String::from).collect();
+        // the queen moved from d8 to h4, both dark squares
+        assert!(lines[0].contains(&format!("{}{}   ", DARK_LAST_MOVE, WHITE_PIECE)));
+        assert!(lines[4].ends_with(&format!("{}{} q {}", DARK_LAST_MOVE, BLACK_PIECE, RESET)));
+        assert!(lines[7].contains(&format!("{}{} K ", CHECK, WHITE_PIECE)));
+        assert_eq!(lines[8], "   a  b  c  d  e  f  g  h ")
+    }
+}
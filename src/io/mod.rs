@@ -1,4 +1,15 @@
-use crate::{board::Board, PieceType, UnitResult};
+use std::fmt::Display;
+
+use regex::Regex;
+
+use crate::{
+    board::{coordinate_to_index, Board},
+    fen::FenError,
+    game::GameStatus,
+    moves::Move,
+    san::SanError,
+    PieceColour, PieceType, UnitResult, STARTING_FEN,
+};
 
 pub mod console;
 pub mod window;
@@ -6,35 +17,321 @@ pub mod window;
 #[derive(Debug)]
 pub enum Command {
     None,
-    Move {
-        start_file: u8,
-        start_rank: u8,
-        end_file: u8,
-        end_rank: u8,
-        promotion_piece: Option<PieceType>,
-    },
+    /// a move entered by a player, which may not be legal
+    Move(Move),
+    ClaimDraw,
+    Undo,
+    /// starts a new game from the given position
+    New(Box<Board>),
+    /// shows the fen of the current position
+    Fen,
+    /// turns the board around
+    Flip,
+    /// lists the legal moves
+    Moves,
+    /// lists the moves played so far
+    History,
+    /// shows the game as pgn
+    Pgn,
+    /// writes the game as pgn to a file
+    Save(String),
+    /// replaces the game with the one in a pgn file
+    Load(String),
+    /// has the engine play a move for the side to move
+    Go,
+    /// shows what the engine thinks of the position
+    Analyse,
+    /// shows the terms of the static evaluation
+    Eval,
+    /// sets the colour the engine plays, or None to turn it off
+    Engine(Option<PieceColour>),
+    Help,
     Quit,
 }
 
-impl From<Vec<&str>> for Command {
-    fn from(value: Vec<&str>) -> Self {
-        if value.len() == 4 {
-            Self::Move {
-                start_file: 1,
-                start_rank: 1,
-                end_file: 1,
-                end_rank: 1,
-                promotion_piece: None,
+/// the commands that can be typed into the console, with a description of each
+pub const HELP: &str = "\
+<move>      make a move, eg. e2e4, e7e8q, Nf3 or O-O
+new [fen]   start a new game, from the starting position or the given fen
+fen         show the fen of the current position
+undo        take back the last move
+flip        turn the board around
+moves       list the legal moves
+history     list the moves played so far
+pgn         show the game as pgn
+save <file> save the game as pgn
+load <file> load a game from a pgn file
+go          let the engine play a move
+analyse     show the engine's evaluation and best line
+eval        explain the evaluation of the position, without searching
+engine <white|black|off>
+            choose the side the engine plays
+draw        claim a draw by threefold repetition or the fifty move rule
+help        show this message
+quit        leave the game";
+
+/// why a line of input could not be turned into a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UNKNOWN_COMMAND(String),
+    INVALID_FEN(FenError),
+    ILLEGAL_MOVE(String),
+    /// a move that fits more than one legal move, eg. "Nd2" when both knights can go there
+    AMBIGUOUS_MOVE(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UNKNOWN_COMMAND(input) => {
+                write!(
+                    f,
+                    "Unknown command '{}', type help for a list of commands",
+                    input
+                )
+            }
+            ParseError::INVALID_FEN(err) => write!(f, "{}", err),
+            ParseError::ILLEGAL_MOVE(input) => write!(f, "Illegal move '{}'", input),
+            ParseError::AMBIGUOUS_MOVE(input) => write!(
+                f,
+                "Ambiguous move '{}', add the file or rank the piece starts on",
+                input
+            ),
+        }
+    }
+}
+
+impl From<ParseError> for String {
+    fn from(value: ParseError) -> Self {
+        value.to_string()
+    }
+}
+
+impl Command {
+    /// parses a line of input. moves can be given in coordinate notation, eg. "e2e4" or "e7e8q",
+    /// or in standard algebraic notation, eg. "Nf3". case is ignored where it isn't needed
+    pub fn parse(input: &str, board: &Board) -> Result<Self, ParseError> {
+        let tokens: Vec<&str> = input.split_whitespace().collect();
+        let token = match tokens.as_slice() {
+            [] => return Ok(Self::None),
+            [command, fen @ ..] if command.eq_ignore_ascii_case("new") => {
+                let fen = if fen.is_empty() {
+                    STARTING_FEN.to_owned()
+                } else {
+                    fen.join(" ")
+                };
+                let board = Board::from_fen(&fen).map_err(ParseError::INVALID_FEN)?;
+                return Ok(Self::New(Box::new(board)));
+            }
+            [command, colour] if command.eq_ignore_ascii_case("engine") => {
+                return match colour.to_ascii_lowercase().as_str() {
+                    "white" => Ok(Self::Engine(Some(PieceColour::WHITE))),
+                    "black" => Ok(Self::Engine(Some(PieceColour::BLACK))),
+                    "off" => Ok(Self::Engine(None)),
+                    _ => Err(ParseError::UNKNOWN_COMMAND(input.trim().to_owned())),
+                };
+            }
+            [command, path @ ..] if command.eq_ignore_ascii_case("save") && !path.is_empty() => {
+                return Ok(Self::Save(path.join(" ")));
+            }
+            [command, path @ ..] if command.eq_ignore_ascii_case("load") && !path.is_empty() => {
+                return Ok(Self::Load(path.join(" ")));
+            }
+            [token] => *token,
+            _ => return Err(ParseError::UNKNOWN_COMMAND(input.trim().to_owned())),
+        };
+
+        match token.to_ascii_lowercase().as_str() {
+            "quit" => return Ok(Self::Quit),
+            "draw" => return Ok(Self::ClaimDraw),
+            "undo" => return Ok(Self::Undo),
+            "fen" => return Ok(Self::Fen),
+            "flip" => return Ok(Self::Flip),
+            "moves" => return Ok(Self::Moves),
+            "history" => return Ok(Self::History),
+            "pgn" => return Ok(Self::Pgn),
+            "go" => return Ok(Self::Go),
+            "analyse" | "analyze" => return Ok(Self::Analyse),
+            "eval" => return Ok(Self::Eval),
+            "help" => return Ok(Self::Help),
+            _ => (),
+        }
+
+        // coordinate notation
+        let re =
+            Regex::new(r"^(?<start>[a-h][1-8])(?<end>[a-h][1-8])(?<promotion>[qrbn])?$").unwrap();
+        if let Some(captures) = re.captures(&token.to_ascii_lowercase()) {
+            let square_index = |name: &str| {
+                let square = captures[name].as_bytes();
+                coordinate_to_index(square[0] - b'a', b'8' - square[1])
+            };
+            let promotion = captures
+                .name("promotion")
+                .map(|promotion| match promotion.as_str() {
+                    "q" => PieceType::QUEEN,
+                    "r" => PieceType::ROOK,
+                    "b" => PieceType::BISHOP,
+                    _ => PieceType::KNIGHT,
+                });
+            let mv = Move::from_squares(square_index("start"), square_index("end"), promotion);
+            return board
+                .find_move(mv)
+                .map(Self::Move)
+                .ok_or(ParseError::ILLEGAL_MOVE(token.to_owned()));
+        }
+
+        // san, as typed and then with the usual capitalisation.
+        // a leading b could be a pawn on the b file or a bishop, so both are tried
+        let normalised = normalise_san(token);
+        let mut candidates = vec![token.to_owned(), normalised.clone()];
+        if let Some(rest) = normalised.strip_prefix('b') {
+            candidates.push(format!("B{}", rest));
+        }
+
+        let mut errors = Vec::new();
+        for candidate in candidates {
+            match board.parse_san(&candidate) {
+                Ok(mv) => return Ok(Self::Move(mv)),
+                Err(err) => errors.push(err),
             }
-        } else if value.len() == 1 && value[0] == "quit" {
-            Self::Quit
+        }
+        if errors.contains(&SanError::AMBIGUOUS_MOVE) {
+            Err(ParseError::AMBIGUOUS_MOVE(token.to_owned()))
+        } else if errors.contains(&SanError::ILLEGAL_MOVE) {
+            Err(ParseError::ILLEGAL_MOVE(token.to_owned()))
         } else {
-            Self::None
+            Err(ParseError::UNKNOWN_COMMAND(token.to_owned()))
+        }
+    }
+}
+
+/// guesses the standard capitalisation of a san move typed in any case, eg. "nxe8=q" -> "Nxe8=Q".
+/// a leading b is left lowercase
+fn normalise_san(token: &str) -> String {
+    let mut chars: Vec<char> = token.to_ascii_lowercase().chars().collect();
+
+    for i in 0..chars.len() {
+        let is_piece_letter = match i {
+            0 => "kqrnp".contains(chars[i]),
+            // promotion, eg. "e8q" or "e8=q"
+            _ => {
+                "qrbn".contains(chars[i]) && (chars[i - 1].is_ascii_digit() || chars[i - 1] == '=')
+            }
+        };
+        // o only appears in castling
+        if is_piece_letter || chars[i] == 'o' {
+            chars[i] = chars[i].to_ascii_uppercase();
         }
     }
+
+    chars.into_iter().collect()
 }
 
 pub trait IO {
     fn get_command(&mut self, board: &Board) -> Result<Command, String>;
     fn render(&mut self, board: &Board) -> UnitResult;
+    /// turns the board around, if the renderer supports it
+    fn flip(&mut self) {}
+    /// tells the renderer the last move played, so it can be highlighted
+    fn set_last_move(&mut self, mv: Option<Move>) {}
+    /// shows the final position and result once the game is over
+    fn show_result(&mut self, board: &Board, status: GameStatus) -> UnitResult;
+    /// stops showing the result when a finished game carries on, eg. after an undo
+    fn hide_result(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_FEN;
+
+    /// parses input and gets the move in coordinate notation
+    fn parse_move(fen: &str, input: &str) -> Result<String, ParseError> {
+        let board = Board::from_fen(fen).unwrap();
+        match Command::parse(input, &board)? {
+            Command::Move(mv) => Ok(mv.to_string()),
+            command => panic!("{:?} is not a move", command),
+        }
+    }
+
+    #[test]
+    fn coordinate_moves() {
+        assert_eq!(parse_move(STARTING_FEN, "e2e4"), Ok("e2e4".to_owned()));
+        assert_eq!(parse_move(STARTING_FEN, " G1F3\n"), Ok("g1f3".to_owned()));
+        let fen = "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(parse_move(fen, "b7b8N"), Ok("b7b8n".to_owned()))
+    }
+    #[test]
+    fn san_moves() {
+        assert_eq!(parse_move(STARTING_FEN, "Nf3"), Ok("g1f3".to_owned()));
+        assert_eq!(parse_move(STARTING_FEN, "nf3"), Ok("g1f3".to_owned()));
+        assert_eq!(parse_move(STARTING_FEN, "E4"), Ok("e2e4".to_owned()));
+        let fen = "4k3/1P6/8/8/8/8/8/2B1K3 w - - 0 1";
+        assert_eq!(parse_move(fen, "b8=q"), Ok("b7b8q".to_owned()));
+        assert_eq!(parse_move(fen, "bd2"), Ok("c1d2".to_owned()));
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(parse_move(fen, "o-o-o"), Ok("e1c1".to_owned()))
+    }
+    #[test]
+    fn commands() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        assert!(matches!(Command::parse("QUIT", &board), Ok(Command::Quit)));
+        assert!(matches!(Command::parse("undo", &board), Ok(Command::Undo)));
+        assert!(matches!(Command::parse("", &board), Ok(Command::None)));
+        assert!(matches!(Command::parse("Flip", &board), Ok(Command::Flip)));
+        assert!(matches!(
+            Command::parse("history", &board),
+            Ok(Command::History)
+        ));
+        assert!(matches!(Command::parse("pgn", &board), Ok(Command::Pgn)));
+        assert!(matches!(
+            Command::parse("engine Black", &board),
+            Ok(Command::Engine(Some(PieceColour::BLACK)))
+        ));
+        assert!(matches!(
+            Command::parse("engine off", &board),
+            Ok(Command::Engine(None))
+        ));
+        match Command::parse("save games/my game.pgn", &board) {
+            Ok(Command::Save(path)) => assert_eq!(path, "games/my game.pgn"),
+            command => panic!("{:?}", command),
+        }
+    }
+    #[test]
+    fn new_game() {
+        let board = Board::from_fen(STARTING_FEN).unwrap();
+        match Command::parse("new", &board) {
+            Ok(Command::New(new)) => assert_eq!(*new, board),
+            command => panic!("{:?}", command),
+        }
+        let fen = "4k3/8/8/8/8/8/8/4K3 b - - 0 1";
+        match Command::parse(&format!("new {}", fen), &board) {
+            Ok(Command::New(new)) => assert_eq!(new.to_fen(), fen),
+            command => panic!("{:?}", command),
+        }
+        assert_eq!(
+            Command::parse("new 4k3/8/8 w - - 0 1", &board).unwrap_err(),
+            ParseError::INVALID_FEN(FenError::WRONG_RANK_COUNT { column: 7 })
+        )
+    }
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_move(STARTING_FEN, "e2e5"),
+            Err(ParseError::ILLEGAL_MOVE("e2e5".to_owned()))
+        );
+        assert_eq!(
+            parse_move(STARTING_FEN, "Nf4"),
+            Err(ParseError::ILLEGAL_MOVE("Nf4".to_owned()))
+        );
+        let fen = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+        assert_eq!(
+            parse_move(fen, "Nd2"),
+            Err(ParseError::AMBIGUOUS_MOVE("Nd2".to_owned()))
+        );
+        assert_eq!(
+            parse_move(STARTING_FEN, "hello"),
+            Err(ParseError::UNKNOWN_COMMAND("hello".to_owned()))
+        )
+    }
 }
@@ -3,7 +3,9 @@ use std::collections::HashMap;
 use raylib::prelude::*;
 
 use crate::board::{coordinate_to_index, index_to_coordinate, Board};
+use crate::game::GameStatus;
 use crate::io::{Command, IO};
+use crate::moves::Move;
 use crate::{PieceColour, PieceType, UnitResult};
 
 pub struct WindowRenderer {
@@ -12,6 +14,7 @@ pub struct WindowRenderer {
     board_texture: Texture2D,
     piece_textures: HashMap<(PieceColour, PieceType), Texture2D>,
     selected_square: Option<(u8, u8)>,
+    result: Option<GameStatus>,
 }
 
 impl WindowRenderer {
@@ -66,6 +69,7 @@ impl WindowRenderer {
             board_texture,
             piece_textures,
             selected_square: None,
+            result: None,
         })
     }
 }
@@ -76,6 +80,15 @@ impl IO for WindowRenderer {
             return Ok(Command::Quit);
         }
 
+        if self.rl.is_key_pressed(KeyboardKey::KEY_D) {
+            return Ok(Command::ClaimDraw);
+        }
+
+        if self.rl.is_key_pressed(KeyboardKey::KEY_U) {
+            self.selected_square = None;
+            return Ok(Command::Undo);
+        }
+
         if self
             .rl
             .is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON)
@@ -91,13 +104,11 @@ impl IO for WindowRenderer {
             }
             if let Some((selected_file, selected_rank)) = self.selected_square {
                 self.selected_square = None;
-                return Ok(Command::Move {
-                    start_file: selected_file,
-                    start_rank: selected_rank,
-                    end_file: file,
-                    end_rank: rank,
-                    promotion_piece: Some(PieceType::QUEEN), // TODO
-                });
+                return Ok(Command::Move(Move::from_squares(
+                    coordinate_to_index(selected_file, selected_rank),
+                    coordinate_to_index(file, rank),
+                    Some(PieceType::QUEEN), // TODO
+                )));
             } else {
                 self.selected_square = None;
             }
@@ -123,6 +134,43 @@ impl IO for WindowRenderer {
             )
         }
 
+        // draw where the selected piece can move
+        if let Some((file, rank)) = self.selected_square {
+            let from = coordinate_to_index(file, rank);
+            for mv in board.legal_moves().iter().filter(|mv| mv.from() == from) {
+                let (end_file, end_rank) = mv.end();
+                d.draw_circle(
+                    100 * end_file as i32 + 50,
+                    100 * end_rank as i32 + 50,
+                    15.0,
+                    Color::new(40, 40, 40, 120), // translucent grey
+                )
+            }
+        }
+
+        // draw check
+        if board.is_in_check(board.turn) {
+            if let Some((file, rank)) = board.king_square(board.turn) {
+                d.draw_rectangle(
+                    100 * file as i32,
+                    100 * rank as i32,
+                    100,
+                    100,
+                    Color::new(220, 40, 40, 180), // translucent red
+                )
+            }
+        }
+
+        // draw the side to move's pieces that the opponent can win
+        for ind in board.hanging_pieces(board.turn) {
+            let (file, rank) = index_to_coordinate(ind);
+            d.draw_rectangle_lines_ex(
+                Rectangle::new(100.0 * file as f32, 100.0 * rank as f32, 100.0, 100.0),
+                4,
+                Color::new(220, 40, 40, 220), // red
+            )
+        }
+
         // draw pieces
         for square in board.squares.iter().enumerate() {
             if let (ind, Some(piece)) = square {
@@ -131,13 +179,37 @@ impl IO for WindowRenderer {
                     .piece_textures
                     .get(&(piece.colour(), piece.piece_type()));
                 if let Some(t) = tex {
-                    d.draw_texture(&t, 100 * file as i32, 100 * rank as i32, Color::WHITE)
+                    d.draw_texture(t, 100 * file as i32, 100 * rank as i32, Color::WHITE)
                 } else {
                     return Err(format!("Texture for {:?} does not exist", piece));
                 }
             }
         }
 
+        // draw result
+        if let Some(status) = self.result {
+            let text = status.to_string();
+            d.draw_rectangle(0, 350, 800, 100, Color::new(0, 0, 0, 180));
+            d.draw_text(
+                &text,
+                400 - measure_text(&text, 40) / 2,
+                380,
+                40,
+                Color::WHITE,
+            );
+        }
+
         Ok(())
     }
+
+    fn show_result(&mut self, board: &Board, status: GameStatus) -> UnitResult {
+        self.result = Some(status);
+        self.selected_square = None;
+
+        Ok(())
+    }
+
+    fn hide_result(&mut self) {
+        self.result = None;
+    }
 }
@@ -1,19 +1,32 @@
 #![allow(dead_code, unused_variables, unused_mut, unused_imports)]
+#![allow(non_camel_case_types, clippy::upper_case_acronyms)]
 
-use std::{ops::Not, result};
+use std::{env, fs, ops::Not, result};
 
+use engine::SearchLimits;
 use game::Game;
-use io::{console::ConsoleRenderer, window::WindowRenderer};
+use io::{
+    console::{ConsoleRenderer, ConsoleStyle},
+    window::WindowRenderer,
+};
 
+mod bitboard;
 mod board;
+mod engine;
+mod fen;
 mod game;
 mod io;
+mod material;
+mod moves;
+mod perft;
+mod pgn;
 mod piece;
+mod san;
+mod zobrist;
 
 type UnitResult = result::Result<(), String>;
 
 const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-const EMPTY_FEN: &str = "8/8/8/8/8/8/8/8 w - - 0 1";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum PieceType {
@@ -57,8 +70,88 @@ impl Not for PieceColour {
 }
 
 fn main() -> UnitResult {
-    let renderer = WindowRenderer::new()?;
-    let mut game = Game::new(renderer)?;
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        // chess perft <fen> <depth>
+        Some("perft") => {
+            let (depth, fen) = args[1..]
+                .split_last()
+                .ok_or("Usage: chess perft <fen> <depth>")?;
+            let depth = depth
+                .parse()
+                .map_err(|_| format!("'{}' is not a valid depth", depth))?;
+            // the fen can be given as one quoted argument or as separate fields
+            perft::run(&fen.join(" "), depth)
+        }
+        Some("console") => console(&args[1..]),
+        _ => {
+            let renderer = WindowRenderer::new()?;
+            let mut game = Game::new(renderer)?;
+
+            game.run()
+        }
+    }
+}
+
+/// chess console [options], plays in the terminal. commands can also be piped in
+fn console(options: &[String]) -> UnitResult {
+    let usage = "chess console [--unicode] [--colour] [--flip] [--pgn <file>] \
+                 [--engine <white|black>] [--depth <n>] [--hash <megabytes>]";
+    let mut style = ConsoleStyle::default();
+    let mut pgn_path = None;
+    let mut engine_colour = None;
+    let mut depth = None;
+    let mut hash = None;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--unicode" => style.unicode = true,
+            "--colour" | "--color" => style.colour = true,
+            "--flip" => style.flipped = true,
+            "--pgn" => pgn_path = Some(options.next().ok_or(usage)?),
+            "--engine" => {
+                engine_colour = match options.next().map(String::as_str) {
+                    Some("white") => Some(PieceColour::WHITE),
+                    Some("black") => Some(PieceColour::BLACK),
+                    _ => return Err(usage.into()),
+                }
+            }
+            "--depth" => {
+                let value = options.next().ok_or(usage)?;
+                depth = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("'{}' is not a valid depth", value))?,
+                );
+            }
+            "--hash" => {
+                let value = options.next().ok_or(usage)?;
+                hash = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("'{}' is not a valid hash size", value))?,
+                );
+            }
+            _ => return Err(format!("Unknown option '{}'. Usage: {}", option, usage)),
+        }
+    }
+    let renderer = ConsoleRenderer::new(style)?;
+    let mut game = match pgn_path {
+        Some(path) => {
+            let pgn =
+                fs::read_to_string(path).map_err(|err| format!("Cannot read {}: {}", path, err))?;
+            Game::from_pgn(renderer, &pgn)?
+        }
+        None => Game::new(renderer)?,
+    };
+    game.engine_colour = engine_colour;
+    if let Some(depth) = depth {
+        game.engine_limits = SearchLimits::depth(depth);
+    }
+    if let Some(megabytes) = hash {
+        game.engine_table_megabytes = megabytes;
+    }
 
     game.run()
 }
@@ -0,0 +1,105 @@
+use crate::{PieceColour, PieceType};
+
+/// a count of the pieces on a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Material {
+    /// indexed by [colour][piece type]
+    counts: [[u8; 6]; 2],
+    /// bishops on light squares, indexed by colour
+    light_bishops: [u8; 2],
+    /// bishops on dark squares, indexed by colour
+    dark_bishops: [u8; 2],
+}
+
+impl Material {
+    /// adds a piece standing on the given square to the count
+    pub fn add(&mut self, colour: PieceColour, piece_type: PieceType, file: u8, rank: u8) {
+        self.counts[colour as usize][piece_type as usize] += 1;
+
+        if piece_type == PieceType::BISHOP {
+            // a8 is a light square
+            if (file + rank).is_multiple_of(2) {
+                self.light_bishops[colour as usize] += 1;
+            } else {
+                self.dark_bishops[colour as usize] += 1;
+            }
+        }
+    }
+
+    /// gets the number of pieces of a type and colour
+    pub fn count(&self, colour: PieceColour, piece_type: PieceType) -> u8 {
+        self.counts[colour as usize][piece_type as usize]
+    }
+
+    /// gets the number of pieces of a type for both colours
+    pub fn total(&self, piece_type: PieceType) -> u8 {
+        self.count(PieceColour::WHITE, piece_type) + self.count(PieceColour::BLACK, piece_type)
+    }
+
+    pub fn light_bishops(&self, colour: PieceColour) -> u8 {
+        self.light_bishops[colour as usize]
+    }
+
+    pub fn dark_bishops(&self, colour: PieceColour) -> u8 {
+        self.dark_bishops[colour as usize]
+    }
+
+    /// checks if neither side can possibly checkmate, ie. K vs K, K+minor vs K,
+    /// or only bishops that all stand on the same colour squares
+    pub fn is_insufficient(&self) -> bool {
+        if self.total(PieceType::PAWN) != 0
+            || self.total(PieceType::ROOK) != 0
+            || self.total(PieceType::QUEEN) != 0
+        {
+            return false;
+        }
+
+        let knights = self.total(PieceType::KNIGHT);
+        let light_bishops: u8 = PieceColour::COLOURS
+            .iter()
+            .map(|&colour| self.light_bishops(colour))
+            .sum();
+        let dark_bishops: u8 = PieceColour::COLOURS
+            .iter()
+            .map(|&colour| self.dark_bishops(colour))
+            .sum();
+
+        match knights {
+            0 => light_bishops == 0 || dark_bishops == 0,
+            1 => light_bishops + dark_bishops == 0,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    #[test]
+    fn king_and_minor_piece() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+        ] {
+            assert!(Board::from_fen(fen).unwrap().material().is_insufficient())
+        }
+    }
+    #[test]
+    fn same_coloured_bishops() {
+        let b = Board::from_fen("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert!(b.material().is_insufficient());
+        let b = Board::from_fen("4k1b1/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert!(!b.material().is_insufficient())
+    }
+    #[test]
+    fn sufficient_material() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4KNN1 w - - 0 1",
+            "4kn2/8/8/8/8/8/8/4KB2 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        ] {
+            assert!(!Board::from_fen(fen).unwrap().material().is_insufficient())
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt::Display;
+
+use crate::board::{index_to_coordinate, index_to_name};
+use crate::PieceType;
+
+/// a move of a piece from one square to another.
+/// moves from the board's move generation also carry flags describing what kind of move it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    from: u8,
+    to: u8,
+    promotion: Option<PieceType>,
+    flags: u8,
+}
+
+impl Move {
+    pub const QUIET: u8 = 0;
+    pub const CAPTURE: u8 = 1;
+    /// also set CAPTURE
+    pub const EN_PASSANT: u8 = 2;
+    pub const CASTLE: u8 = 4;
+    pub const DOUBLE_PUSH: u8 = 8;
+
+    pub fn new(from: usize, to: usize, promotion: Option<PieceType>, flags: u8) -> Self {
+        Self {
+            from: from as u8,
+            to: to as u8,
+            promotion,
+            flags,
+        }
+    }
+
+    /// a move as entered by a player, without flags.
+    /// Board::find_move turns it into the matching legal move
+    pub fn from_squares(from: usize, to: usize, promotion: Option<PieceType>) -> Self {
+        Self::new(from, to, promotion, Self::QUIET)
+    }
+
+    /// index of the square the piece moves from
+    pub fn from(&self) -> usize {
+        self.from as usize
+    }
+
+    /// index of the square the piece moves to
+    pub fn to(&self) -> usize {
+        self.to as usize
+    }
+
+    /// (file, rank) the piece moves from
+    pub fn start(&self) -> (u8, u8) {
+        index_to_coordinate(self.from())
+    }
+
+    /// (file, rank) the piece moves to
+    pub fn end(&self) -> (u8, u8) {
+        index_to_coordinate(self.to())
+    }
+
+    pub fn promotion(&self) -> Option<PieceType> {
+        self.promotion
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.flags & Self::CAPTURE != 0
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.flags & Self::EN_PASSANT != 0
+    }
+
+    pub fn is_castle(&self) -> bool {
+        self.flags & Self::CASTLE != 0
+    }
+
+    pub fn is_double_push(&self) -> bool {
+        self.flags & Self::DOUBLE_PUSH != 0
+    }
+
+    pub fn is_promotion(&self) -> bool {
+        self.promotion.is_some()
+    }
+}
+
+impl Display for Move {
+    /// formats the move in coordinate notation, eg. "e2e4" or "e7e8q"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            index_to_name(self.from()),
+            index_to_name(self.to())
+        )?;
+        match self.promotion {
+            Some(PieceType::QUEEN) => write!(f, "q"),
+            Some(PieceType::ROOK) => write!(f, "r"),
+            Some(PieceType::BISHOP) => write!(f, "b"),
+            Some(PieceType::KNIGHT) => write!(f, "n"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::coordinate_to_index;
+    #[test]
+    fn size() {
+        assert_eq!(std::mem::size_of::<Move>(), 4)
+    }
+    #[test]
+    fn coordinate_notation() {
+        let mv = Move::from_squares(
+            coordinate_to_index(4, 1),
+            coordinate_to_index(4, 0),
+            Some(PieceType::QUEEN),
+        );
+        assert_eq!(mv.to_string(), "e7e8q")
+    }
+}
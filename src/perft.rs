@@ -0,0 +1,135 @@
+use std::time::Instant;
+
+use crate::board::Board;
+use crate::moves::Move;
+use crate::UnitResult;
+
+impl Board {
+    /// counts the leaf nodes of the legal move tree to a given depth
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        let moves = self.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+
+        let mut nodes = 0;
+        for mv in moves {
+            let undo = self.make(mv);
+            nodes += self.perft(depth - 1);
+            self.unmake(mv, undo);
+        }
+        nodes
+    }
+
+    /// perft split by the first move, useful for finding which move a bug is under
+    pub fn divide(&mut self, depth: u32) -> Vec<(Move, u64)> {
+        let mut results = Vec::new();
+        if depth == 0 {
+            return results;
+        }
+
+        for mv in self.legal_moves() {
+            let undo = self.make(mv);
+            results.push((mv, self.perft(depth - 1)));
+            self.unmake(mv, undo);
+        }
+        results
+    }
+}
+
+/// runs divide on a position and prints the results
+pub fn run(fen: &str, depth: u32) -> UnitResult {
+    let mut board = Board::from_fen(fen)?;
+
+    let start = Instant::now();
+    let results = board.divide(depth);
+    let elapsed = start.elapsed();
+
+    let mut nodes = 0;
+    for (mv, count) in results {
+        println!("{}: {}", mv, count);
+        nodes += count;
+    }
+    println!();
+    println!("Nodes searched: {}", nodes);
+    println!(
+        "Time: {:.3}s ({:.0} nodes/s)",
+        elapsed.as_secs_f64(),
+        nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::STARTING_FEN;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    /// checks perft for each depth, starting from 1
+    fn check(fen: &str, expected: &[u64]) {
+        let mut board = Board::from_fen(fen).unwrap();
+        for (depth, &nodes) in expected.iter().enumerate() {
+            assert_eq!(board.perft(depth as u32 + 1), nodes, "depth {}", depth + 1);
+        }
+        assert_eq!(board, Board::from_fen(fen).unwrap());
+    }
+
+    #[test]
+    fn starting_position() {
+        check(STARTING_FEN, &[20, 400, 8902])
+    }
+    #[test]
+    fn kiwipete() {
+        check(KIWIPETE, &[48, 2039, 97862])
+    }
+    #[test]
+    fn position_3() {
+        check(POSITION_3, &[14, 191, 2812, 43238])
+    }
+    #[test]
+    fn position_4() {
+        check(POSITION_4, &[6, 264, 9467])
+    }
+    #[test]
+    fn position_5() {
+        check(POSITION_5, &[44, 1486, 62379])
+    }
+    #[test]
+    fn position_6() {
+        check(POSITION_6, &[46, 2079, 89890])
+    }
+    /// the next depth of each position, too slow to run by default
+    #[test]
+    #[ignore]
+    fn deep() {
+        for (fen, depth, nodes) in [
+            (STARTING_FEN, 5, 4865609),
+            (KIWIPETE, 4, 4085603),
+            (POSITION_3, 5, 674624),
+            (POSITION_4, 4, 422333),
+            (POSITION_5, 4, 2103487),
+            (POSITION_6, 4, 3894594),
+        ] {
+            let mut board = Board::from_fen(fen).unwrap();
+            assert_eq!(board.perft(depth), nodes, "{}", fen);
+        }
+    }
+    #[test]
+    fn divide_sums_to_perft() {
+        let mut board = Board::from_fen(KIWIPETE).unwrap();
+        let total: u64 = board.divide(2).iter().map(|(_, nodes)| nodes).sum();
+        assert_eq!(total, 2039)
+    }
+}
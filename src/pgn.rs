@@ -0,0 +1,458 @@
+use std::fmt::Display;
+
+use regex::Regex;
+
+use crate::fen::FenError;
+use crate::san::SanError;
+use crate::PieceColour;
+
+/// the tags every pgn game has, in the order they are written
+pub const SEVEN_TAG_ROSTER: [&str; 7] =
+    ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// why a pgn file could not be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// a tag pair that isn't of the form [Name "value"]
+    INVALID_TAG(String),
+    /// a '{' comment with no closing '}'
+    UNCLOSED_COMMENT,
+    /// a '(' without a matching ')' or the other way round
+    UNBALANCED_VARIATION,
+    /// a '(' before any move it could be played instead of
+    MISPLACED_VARIATION,
+    INVALID_FEN(FenError),
+    /// a move that isn't legal in the position it is played in
+    INVALID_MOVE {
+        ply: usize,
+        san: String,
+        error: SanError,
+    },
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::INVALID_TAG(tag) => write!(f, "Invalid pgn tag '[{}]'", tag),
+            PgnError::UNCLOSED_COMMENT => write!(f, "Invalid pgn: comment is never closed"),
+            PgnError::UNBALANCED_VARIATION => {
+                write!(f, "Invalid pgn: variation brackets don't match")
+            }
+            PgnError::MISPLACED_VARIATION => {
+                write!(f, "Invalid pgn: variation before the first move")
+            }
+            PgnError::INVALID_FEN(err) => write!(f, "{}", err),
+            PgnError::INVALID_MOVE { ply, san, error } => {
+                write!(f, "Invalid pgn move '{}' at ply {}. {}", san, ply, error)
+            }
+        }
+    }
+}
+
+impl From<PgnError> for String {
+    fn from(value: PgnError) -> Self {
+        value.to_string()
+    }
+}
+
+/// what is written about a move besides the move itself
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Annotation {
+    /// numeric annotation glyphs, eg. 4 for "??" or "$4"
+    pub nags: Vec<u8>,
+    /// comments after the move, without their braces
+    pub comments: Vec<String>,
+    /// lines that could have been played instead of the move
+    pub variations: Vec<PgnLine>,
+}
+
+/// a move in standard algebraic notation with its annotation
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PgnMove {
+    pub san: String,
+    pub annotation: Annotation,
+}
+
+/// a sequence of moves, either the main line of a game or a variation
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PgnLine {
+    /// comments before the first move
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnMove>,
+}
+
+impl PgnLine {
+    /// adds a comment to the last move, or before the first one if there are no moves yet
+    fn add_comment(&mut self, comment: &str) {
+        let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+        if comment.is_empty() {
+            return;
+        }
+        match self.moves.last_mut() {
+            Some(mv) => mv.annotation.comments.push(comment),
+            None => self.comments.push(comment),
+        }
+    }
+
+    /// writes the line as movetext, numbering the moves from the position it starts in.
+    /// a black move is numbered at the start of the line and after a comment or variation
+    pub fn to_movetext(&self, fullmove_number: u32, turn: PieceColour) -> String {
+        let mut tokens: Vec<String> = self.comments.iter().map(|c| format!("{{{}}}", c)).collect();
+        let mut fullmove_number = fullmove_number;
+        let mut turn = turn;
+        let mut needs_number = true;
+
+        for mv in &self.moves {
+            let annotation = &mv.annotation;
+            let mut token = match turn {
+                PieceColour::WHITE => format!("{}. ", fullmove_number),
+                PieceColour::BLACK if needs_number => format!("{}... ", fullmove_number),
+                PieceColour::BLACK => String::new(),
+            };
+            token.push_str(&mv.san);
+
+            // the first glyph is written as a suffix if it has one, eg. "Nf6??"
+            let mut nags = annotation.nags.iter().peekable();
+            if let Some(&(suffix, _)) = nags
+                .peek()
+                .and_then(|&&nag| SUFFIX_NAGS.iter().find(|&&(_, n)| n == nag))
+            {
+                token.push_str(suffix);
+                nags.next();
+            }
+            tokens.push(token);
+            tokens.extend(nags.map(|nag| format!("${}", nag)));
+            tokens.extend(annotation.comments.iter().map(|c| format!("{{{}}}", c)));
+            tokens.extend(
+                annotation
+                    .variations
+                    .iter()
+                    .map(|variation| format!("({})", variation.to_movetext(fullmove_number, turn))),
+            );
+
+            needs_number = !annotation.comments.is_empty() || !annotation.variations.is_empty();
+            if turn == PieceColour::BLACK {
+                fullmove_number += 1;
+            }
+            turn = !turn;
+        }
+
+        tokens.join(" ")
+    }
+}
+
+/// the numeric annotation glyphs that have a suffix form
+const SUFFIX_NAGS: [(&str, u8); 6] = [
+    ("!", 1),
+    ("?", 2),
+    ("!!", 3),
+    ("??", 4),
+    ("!?", 5),
+    ("?!", 6),
+];
+
+/// a game as written in pgn, before its moves are checked.
+/// comments, nags and variations are kept with the moves they follow
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pgn {
+    /// tag pairs in the order they appear
+    pub tags: Vec<(String, String)>,
+    /// the main line
+    pub line: PgnLine,
+    /// the game termination marker, eg. "1-0" or "*"
+    pub result: Option<String>,
+}
+
+impl Pgn {
+    /// gets the value of a tag
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// reads the first game in a pgn file
+    pub fn parse(text: &str) -> Result<Self, PgnError> {
+        Ok(Self::parse_all(text)?
+            .into_iter()
+            .next()
+            .unwrap_or_default())
+    }
+
+    /// reads every game in a pgn file
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, PgnError> {
+        let mut games = Vec::new();
+        let mut game = Self::default();
+        // the variations being read, innermost last
+        let mut variations: Vec<PgnLine> = Vec::new();
+        let mut at_line_start = true;
+        let mut chars = text.chars().peekable();
+
+        while let Some(chr) = chars.next() {
+            let in_variation = !variations.is_empty();
+            // moves and comments go into the innermost variation, or the main line
+            let line = variations.last_mut().unwrap_or(&mut game.line);
+            match chr {
+                '\n' => {
+                    at_line_start = true;
+                    continue;
+                }
+                // a line starting with % is ignored
+                '%' if at_line_start => while chars.next_if(|&chr| chr != '\n').is_some() {},
+                '{' => {
+                    let mut comment = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(chr) => comment.push(chr),
+                            None => return Err(PgnError::UNCLOSED_COMMENT),
+                        }
+                    }
+                    line.add_comment(&comment);
+                }
+                ';' => {
+                    let mut comment = String::new();
+                    while let Some(chr) = chars.next_if(|&chr| chr != '\n') {
+                        comment.push(chr);
+                    }
+                    line.add_comment(&comment);
+                }
+                '(' => {
+                    if line.moves.is_empty() {
+                        return Err(PgnError::MISPLACED_VARIATION);
+                    }
+                    variations.push(PgnLine::default());
+                }
+                ')' => {
+                    let variation = variations.pop().ok_or(PgnError::UNBALANCED_VARIATION)?;
+                    let line = variations.last_mut().unwrap_or(&mut game.line);
+                    if let Some(mv) = line.moves.last_mut() {
+                        mv.annotation.variations.push(variation);
+                    }
+                }
+                '[' if !in_variation => {
+                    // tags after movetext belong to the next game, even without a result
+                    if !game.line.moves.is_empty() {
+                        games.push(std::mem::take(&mut game));
+                    }
+                    let mut tag = String::new();
+                    let mut in_string = false;
+                    let mut escaped = false;
+                    for chr in chars.by_ref() {
+                        if chr == ']' && !in_string {
+                            break;
+                        }
+                        in_string ^= chr == '"' && !escaped;
+                        escaped = chr == '\\' && !escaped;
+                        tag.push(chr);
+                    }
+                    game.tags.push(parse_tag(&tag)?);
+                }
+                chr if chr.is_whitespace() => (),
+                chr => {
+                    let mut symbol = chr.to_string();
+                    while let Some(chr) =
+                        chars.next_if(|chr| !chr.is_whitespace() && !"{}();[]".contains(*chr))
+                    {
+                        symbol.push(chr);
+                    }
+                    match symbol.as_str() {
+                        "1-0" | "0-1" | "1/2-1/2" | "*" if !in_variation => {
+                            game.result = Some(symbol);
+                            games.push(std::mem::take(&mut game));
+                        }
+                        "1-0" | "0-1" | "1/2-1/2" | "*" => (),
+                        _ => {
+                            if let Some(nag) = symbol.strip_prefix('$') {
+                                if let (Some(mv), Ok(nag)) = (line.moves.last_mut(), nag.parse()) {
+                                    mv.annotation.nags.push(nag);
+                                }
+                            } else if let Some((san, suffix)) = move_in_symbol(&symbol) {
+                                line.moves.push(PgnMove {
+                                    san: san.to_owned(),
+                                    annotation: Annotation {
+                                        nags: suffix.into_iter().collect(),
+                                        ..Annotation::default()
+                                    },
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+            at_line_start = false;
+        }
+
+        if !variations.is_empty() {
+            return Err(PgnError::UNBALANCED_VARIATION);
+        }
+        if !game.tags.is_empty() || !game.line.moves.is_empty() {
+            games.push(game);
+        }
+        Ok(games)
+    }
+}
+
+/// reads the inside of a tag pair, eg. `Event "Casual game"`
+fn parse_tag(tag: &str) -> Result<(String, String), PgnError> {
+    let re = Regex::new(r#"^\s*(?<name>[A-Za-z0-9_]+)\s+"(?<value>(?:[^"\\]|\\.)*)"\s*$"#).unwrap();
+    let captures = re
+        .captures(tag)
+        .ok_or(PgnError::INVALID_TAG(tag.to_owned()))?;
+    let mut value = String::new();
+    let mut chars = captures["value"].chars();
+    while let Some(chr) = chars.next() {
+        // \" and \\ stand for " and \
+        value.push(match chr {
+            '\\' => chars.next().unwrap_or('\\'),
+            chr => chr,
+        });
+    }
+    Ok((captures["name"].to_owned(), value))
+}
+
+/// gets the move from a piece of movetext, skipping move numbers like "12." or "12...".
+/// a suffix annotation like "!?" is turned into its numeric annotation glyph
+fn move_in_symbol(symbol: &str) -> Option<(&str, Option<u8>)> {
+    // the move number can be written right before the move, eg. "1.e4"
+    let after_number = symbol.trim_start_matches(|chr: char| chr.is_ascii_digit());
+    let san = if after_number.len() < symbol.len() && after_number.starts_with('.') {
+        after_number.trim_start_matches('.')
+    } else {
+        symbol
+    };
+    let without_suffix = san.trim_end_matches(['!', '?']);
+    let suffix = &san[without_suffix.len()..];
+    let nag = SUFFIX_NAGS
+        .iter()
+        .find(|&&(symbol, _)| symbol == suffix)
+        .map(|&(_, nag)| nag);
+    if without_suffix.is_empty() {
+        None
+    } else {
+        Some((without_suffix, nag))
+    }
+}
+
+/// puts the tag value in quotes, escaping quotes and backslashes
+pub fn quote_tag(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// splits movetext into lines of at most 80 characters, breaking between tokens
+pub fn wrap_movetext(movetext: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for token in movetext.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + token.len() <= 80 => {
+                line.push(' ');
+                line.push_str(token);
+            }
+            _ => lines.push(token.to_owned()),
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sans(line: &PgnLine) -> Vec<&str> {
+        line.moves.iter().map(|mv| mv.san.as_str()).collect()
+    }
+
+    #[test]
+    fn tags_and_moves() {
+        let pgn = Pgn::parse(
+            r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[White "Fischer, Robert J."]
+[Annotator "A \"quoted\" name"]
+
+1. e4 e5 2. Nf3 Nc6 3.Bb5 a6 1/2-1/2"#,
+        )
+        .unwrap();
+        assert_eq!(pgn.tag("Site"), Some("Belgrade, Serbia JUG"));
+        assert_eq!(pgn.tag("Annotator"), Some("A \"quoted\" name"));
+        assert_eq!(pgn.tag("Round"), None);
+        assert_eq!(sans(&pgn.line), vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]);
+        assert_eq!(pgn.result.as_deref(), Some("1/2-1/2"))
+    }
+    #[test]
+    fn comments_nags_and_variations() {
+        let pgn = Pgn::parse(
+            "% a line to skip\n\
+             {a game comment} 1. e4 {best by test} e5 $1 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6!? ; a rest of line comment\n\
+             3. 0-0 *",
+        )
+        .unwrap();
+        assert!(pgn.tags.is_empty());
+        assert_eq!(sans(&pgn.line), vec!["e4", "e5", "Nf3", "Nc6", "0-0"]);
+        assert_eq!(pgn.line.comments, ["a game comment"]);
+        let moves = &pgn.line.moves;
+        assert_eq!(moves[0].annotation.comments, ["best by test"]);
+        assert_eq!(moves[1].annotation.nags, [1]);
+        assert_eq!(moves[3].annotation.nags, [5]);
+        assert_eq!(moves[3].annotation.comments, ["a rest of line comment"]);
+
+        // the variation replaces 2. Nf3, and has one of its own replacing 2... exf4
+        let variation = &moves[2].annotation.variations[0];
+        assert_eq!(sans(variation), vec!["f4", "exf4", "Nf3"]);
+        assert_eq!(
+            sans(&variation.moves[1].annotation.variations[0]),
+            vec!["d5"]
+        );
+        assert_eq!(pgn.result.as_deref(), Some("*"))
+    }
+    #[test]
+    fn writing_movetext() {
+        let text = "{a game comment} 1. e4 {best by test} 1... e5! $14 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) \
+                    2... Nc6!? {a rest of line comment} 3. O-O";
+        let pgn = Pgn::parse(text).unwrap();
+        assert_eq!(pgn.line.to_movetext(1, PieceColour::WHITE), text);
+
+        let pgn = Pgn::parse("1... Kd7 2. e4 $4").unwrap();
+        assert_eq!(
+            pgn.line.to_movetext(1, PieceColour::BLACK),
+            "1... Kd7 2. e4??"
+        )
+    }
+    #[test]
+    fn several_games() {
+        let games = Pgn::parse_all("[Round \"1\"]\n1. d4 1-0\n\n[Round \"2\"]\n1. c4 0-1").unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].tag("Round"), Some("2"));
+        assert_eq!(sans(&games[1].line), vec!["c4"])
+    }
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Pgn::parse("1. e4 {unfinished"),
+            Err(PgnError::UNCLOSED_COMMENT)
+        );
+        assert_eq!(
+            Pgn::parse("1. e4 (1. d4"),
+            Err(PgnError::UNBALANCED_VARIATION)
+        );
+        assert_eq!(
+            Pgn::parse("(1. d4) 1. e4"),
+            Err(PgnError::MISPLACED_VARIATION)
+        );
+        assert_eq!(
+            Pgn::parse("[Event]"),
+            Err(PgnError::INVALID_TAG("Event".to_owned()))
+        )
+    }
+    #[test]
+    fn wrapping() {
+        let movetext = "1. e4 e5 ".repeat(20);
+        let wrapped = wrap_movetext(&movetext);
+        assert!(wrapped.lines().all(|line| line.len() <= 80));
+        assert_eq!(
+            wrapped.split_whitespace().collect::<Vec<_>>(),
+            movetext.split_whitespace().collect::<Vec<_>>()
+        )
+    }
+}
@@ -1,6 +1,8 @@
 use crate::{
+    bitboard::{self, SquareSet},
     board::{coordinate_to_index, Board},
-    piece::Piece,
+    moves::Move,
+    piece::{moves_to, Piece},
     PieceColour, PieceType,
 };
 
@@ -12,10 +14,7 @@ pub struct King {
 }
 
 impl Piece for King {
-    fn new(is_white: bool, file: u8, rank: u8) -> Self
-    where
-        Self: Sized,
-    {
+    fn new(is_white: bool, file: u8, rank: u8) -> Self {
         Self {
             colour: if is_white {
                 PieceColour::WHITE
@@ -28,25 +27,62 @@ impl Piece for King {
     }
 
     fn is_pseudo_legal(&self, new_file: u8, new_rank: u8, board: &Board) -> bool {
-        if let Some(piece) = &board.squares[coordinate_to_index(new_file, new_rank)] {
-            if piece.colour() == self.colour {
-                return false;
-            }
+        let target = coordinate_to_index(new_file, new_rank);
+        if board.colour_pieces(self.colour).contains(target) {
+            return false;
         }
 
-        let file_diff = new_file.abs_diff(self.file);
-        let rank_diff = new_rank.abs_diff(self.rank);
-
-        if file_diff <= 1 && rank_diff <= 1 && file_diff + rank_diff != 0 {
+        if self.attacks(board).contains(target) {
             return true;
         }
 
+        // castling, whether the king passes through check is left to the board
+        let home_rank = match self.colour {
+            PieceColour::WHITE => 7,
+            PieceColour::BLACK => 0,
+        };
+        if self.file == 4 && self.rank == home_rank && new_rank == home_rank {
+            let (has_right, rook_file, between_files) = match new_file {
+                6 => (board.castling_rights.king_side(self.colour), 7, 5..=6),
+                2 => (board.castling_rights.queen_side(self.colour), 0, 1..=3),
+                _ => return false,
+            };
+
+            let has_rook = match &board.squares[coordinate_to_index(rook_file, home_rank)] {
+                Some(piece) => {
+                    piece.colour() == self.colour && piece.piece_type() == PieceType::ROOK
+                }
+                None => false,
+            };
+            let is_clear = between_files
+                .into_iter()
+                .all(|file| board.squares[coordinate_to_index(file, home_rank)].is_none());
+
+            return has_right && has_rook && is_clear;
+        }
+
         false
     }
 
-    fn update_pos(&mut self, new_file: u8, new_rank: u8) {
-        self.file = new_file;
-        self.rank = new_rank;
+    fn pseudo_legal_moves(&self, board: &Board) -> impl Iterator<Item = Move> {
+        let from = coordinate_to_index(self.file, self.rank);
+        let targets = self.attacks(board) & !board.colour_pieces(self.colour);
+        let castles = [2, 6].map(|file| {
+            (self.file == 4 && self.is_pseudo_legal(file, self.rank, board)).then(|| {
+                Move::new(
+                    from,
+                    coordinate_to_index(file, self.rank),
+                    None,
+                    Move::CASTLE,
+                )
+            })
+        });
+
+        moves_to(from, targets, board).chain(castles.into_iter().flatten())
+    }
+
+    fn attacks(&self, board: &Board) -> SquareSet {
+        bitboard::king_attacks(coordinate_to_index(self.file, self.rank))
     }
 
     fn colour(&self) -> PieceColour {
@@ -61,23 +97,43 @@ impl Piece for King {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::EMPTY_FEN;
     #[test]
     fn test1() {
         let k = King::new(true, 0, 0);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(k.is_pseudo_legal(1, 1, b))
     }
     #[test]
     fn test2() {
         let k = King::new(false, 7, 7);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(!k.is_pseudo_legal(7, 7, b))
     }
     #[test]
     fn test3() {
         let k = King::new(true, 2, 4);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(k.is_pseudo_legal(3, 3, b))
     }
+    #[test]
+    fn test4() {
+        let k = King::new(true, 4, 7);
+        let b = &Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(k.is_pseudo_legal(6, 7, b))
+    }
+    #[test]
+    fn test5() {
+        let k = King::new(true, 4, 7);
+        let b = &Board::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+        assert!(!k.is_pseudo_legal(6, 7, b))
+    }
+    #[test]
+    fn test6() {
+        let k = King::new(true, 4, 7);
+        let b = &Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(
+            k.pseudo_legal_moves(b).filter(|mv| mv.is_castle()).count(),
+            1
+        )
+    }
 }
@@ -1,6 +1,8 @@
 use crate::{
+    bitboard::{self, SquareSet},
     board::{coordinate_to_index, Board},
-    piece::Piece,
+    moves::Move,
+    piece::{moves_to, Piece},
     PieceColour, PieceType,
 };
 
@@ -12,10 +14,7 @@ pub struct Knight {
 }
 
 impl Piece for Knight {
-    fn new(is_white: bool, file: u8, rank: u8) -> Self
-    where
-        Self: Sized,
-    {
+    fn new(is_white: bool, file: u8, rank: u8) -> Self {
         Self {
             colour: if is_white {
                 PieceColour::WHITE
@@ -28,24 +27,17 @@ impl Piece for Knight {
     }
 
     fn is_pseudo_legal(&self, new_file: u8, new_rank: u8, board: &Board) -> bool {
-        if let Some(piece) = &board.squares[coordinate_to_index(new_file, new_rank)] {
-            if piece.colour() == self.colour {
-                return false;
-            }
-        }
-
-        let diff = (new_file.abs_diff(self.file), new_rank.abs_diff(self.rank));
-
-        if diff == (2, 1) || diff == (1, 2) {
-            return true;
-        }
+        let targets = self.attacks(board) & !board.colour_pieces(self.colour);
+        targets.contains(coordinate_to_index(new_file, new_rank))
+    }
 
-        false
+    fn pseudo_legal_moves(&self, board: &Board) -> impl Iterator<Item = Move> {
+        let targets = self.attacks(board) & !board.colour_pieces(self.colour);
+        moves_to(coordinate_to_index(self.file, self.rank), targets, board)
     }
 
-    fn update_pos(&mut self, new_file: u8, new_rank: u8) {
-        self.file = new_file;
-        self.rank = new_rank;
+    fn attacks(&self, board: &Board) -> SquareSet {
+        bitboard::knight_attacks(coordinate_to_index(self.file, self.rank))
     }
 
     fn colour(&self) -> PieceColour {
@@ -60,23 +52,28 @@ impl Piece for Knight {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::EMPTY_FEN;
     #[test]
     fn test1() {
         let k = Knight::new(true, 0, 0);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(k.is_pseudo_legal(2, 1, b))
     }
     #[test]
     fn test2() {
         let k = Knight::new(false, 7, 7);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(!k.is_pseudo_legal(5, 5, b))
     }
     #[test]
     fn test3() {
         let k = Knight::new(true, 2, 4);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(k.is_pseudo_legal(3, 2, b))
     }
+    #[test]
+    fn test4() {
+        let k = Knight::new(true, 0, 0);
+        let b = &Board::empty();
+        assert_eq!(k.pseudo_legal_moves(b).count(), 2)
+    }
 }
@@ -12,22 +12,33 @@ pub use pawn::Pawn;
 pub use queen::Queen;
 pub use rook::Rook;
 
-use std::fmt::Debug;
-
-use crate::{board::Board, PieceColour, PieceType};
+use crate::{
+    bitboard::SquareSet,
+    board::{coordinate_to_index, Board},
+    moves::Move,
+    PieceColour, PieceType,
+};
 
+/// the move rules of one type of piece, for a piece standing on a given square
 pub trait Piece {
     /// constructor
-    fn new(is_white: bool, file: u8, rank: u8) -> Self
-    where
-        Self: Sized;
+    fn new(is_white: bool, file: u8, rank: u8) -> Self;
 
     /// checks if a given square can be moved to.
     /// assumes that bounds have been checked and it is the correct colour's turn
     fn is_pseudo_legal(&self, new_file: u8, new_rank: u8, board: &Board) -> bool;
 
-    /// updates the piece's position
-    fn update_pos(&mut self, new_file: u8, new_rank: u8);
+    /// gets every move the piece could make, ignoring whether it leaves its king in check
+    fn pseudo_legal_moves(&self, board: &Board) -> impl Iterator<Item = Move>;
+
+    /// gets the squares the piece attacks, ie. could capture an enemy piece on
+    fn attacks(&self, board: &Board) -> SquareSet;
+
+    /// checks if the piece attacks a given square
+    fn attacks_square(&self, file: u8, rank: u8, board: &Board) -> bool {
+        self.attacks(board)
+            .contains(coordinate_to_index(file, rank))
+    }
 
     /// gets colour of piece
     fn colour(&self) -> PieceColour;
@@ -36,8 +47,106 @@ pub trait Piece {
     fn piece_type(&self) -> PieceType;
 }
 
-impl Debug for dyn Piece {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Piece{{{:?}, {:?}}}", self.colour(), self.piece_type())
+/// turns a set of target squares into moves from a square, flagging captures
+fn moves_to(from: usize, targets: SquareSet, board: &Board) -> impl Iterator<Item = Move> {
+    let occupied = board.occupied();
+    targets.map(move |to| {
+        let flags = if occupied.contains(to) {
+            Move::CAPTURE
+        } else {
+            Move::QUIET
+        };
+        Move::new(from, to, None, flags)
+    })
+}
+
+/// a piece as it is stored on the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColouredPiece {
+    colour: PieceColour,
+    piece_type: PieceType,
+}
+
+impl ColouredPiece {
+    pub fn new(colour: PieceColour, piece_type: PieceType) -> Self {
+        Self { colour, piece_type }
+    }
+
+    pub fn colour(&self) -> PieceColour {
+        self.colour
+    }
+
+    pub fn piece_type(&self) -> PieceType {
+        self.piece_type
+    }
+
+    /// checks if the piece, standing on (file, rank), can move to a given square
+    pub fn is_pseudo_legal(
+        &self,
+        file: u8,
+        rank: u8,
+        new_file: u8,
+        new_rank: u8,
+        board: &Board,
+    ) -> bool {
+        let is_white = self.colour == PieceColour::WHITE;
+        match self.piece_type {
+            PieceType::KING => {
+                King::new(is_white, file, rank).is_pseudo_legal(new_file, new_rank, board)
+            }
+            PieceType::QUEEN => {
+                Queen::new(is_white, file, rank).is_pseudo_legal(new_file, new_rank, board)
+            }
+            PieceType::ROOK => {
+                Rook::new(is_white, file, rank).is_pseudo_legal(new_file, new_rank, board)
+            }
+            PieceType::BISHOP => {
+                Bishop::new(is_white, file, rank).is_pseudo_legal(new_file, new_rank, board)
+            }
+            PieceType::KNIGHT => {
+                Knight::new(is_white, file, rank).is_pseudo_legal(new_file, new_rank, board)
+            }
+            PieceType::PAWN => {
+                Pawn::new(is_white, file, rank).is_pseudo_legal(new_file, new_rank, board)
+            }
+        }
+    }
+
+    /// adds the pseudo-legal moves of the piece, standing on (file, rank), to moves
+    pub fn pseudo_legal_moves(&self, file: u8, rank: u8, board: &Board, moves: &mut Vec<Move>) {
+        let is_white = self.colour == PieceColour::WHITE;
+        match self.piece_type {
+            PieceType::KING => {
+                moves.extend(King::new(is_white, file, rank).pseudo_legal_moves(board))
+            }
+            PieceType::QUEEN => {
+                moves.extend(Queen::new(is_white, file, rank).pseudo_legal_moves(board))
+            }
+            PieceType::ROOK => {
+                moves.extend(Rook::new(is_white, file, rank).pseudo_legal_moves(board))
+            }
+            PieceType::BISHOP => {
+                moves.extend(Bishop::new(is_white, file, rank).pseudo_legal_moves(board))
+            }
+            PieceType::KNIGHT => {
+                moves.extend(Knight::new(is_white, file, rank).pseudo_legal_moves(board))
+            }
+            PieceType::PAWN => {
+                moves.extend(Pawn::new(is_white, file, rank).pseudo_legal_moves(board))
+            }
+        }
+    }
+
+    /// gets the squares the piece, standing on (file, rank), attacks
+    pub fn attacks(&self, file: u8, rank: u8, board: &Board) -> SquareSet {
+        let is_white = self.colour == PieceColour::WHITE;
+        match self.piece_type {
+            PieceType::KING => King::new(is_white, file, rank).attacks(board),
+            PieceType::QUEEN => Queen::new(is_white, file, rank).attacks(board),
+            PieceType::ROOK => Rook::new(is_white, file, rank).attacks(board),
+            PieceType::BISHOP => Bishop::new(is_white, file, rank).attacks(board),
+            PieceType::KNIGHT => Knight::new(is_white, file, rank).attacks(board),
+            PieceType::PAWN => Pawn::new(is_white, file, rank).attacks(board),
+        }
     }
 }
@@ -1,9 +1,18 @@
 use crate::{
-    board::{coordinate_to_index, Board},
+    bitboard::{self, SquareSet},
+    board::{coordinate_to_index, index_to_coordinate, Board},
+    moves::Move,
     piece::Piece,
     PieceColour, PieceType,
 };
 
+const PROMOTIONS: [Option<PieceType>; 4] = [
+    Some(PieceType::QUEEN),
+    Some(PieceType::ROOK),
+    Some(PieceType::BISHOP),
+    Some(PieceType::KNIGHT),
+];
+
 #[derive(Clone, Copy, Debug)]
 pub struct Pawn {
     colour: PieceColour,
@@ -12,10 +21,7 @@ pub struct Pawn {
 }
 
 impl Piece for Pawn {
-    fn new(is_white: bool, file: u8, rank: u8) -> Self
-    where
-        Self: Sized,
-    {
+    fn new(is_white: bool, file: u8, rank: u8) -> Self {
         Self {
             colour: if is_white {
                 PieceColour::WHITE
@@ -28,67 +34,43 @@ impl Piece for Pawn {
     }
 
     fn is_pseudo_legal(&self, new_file: u8, new_rank: u8, board: &Board) -> bool {
-        let direction: i32 = match self.colour {
-            PieceColour::WHITE => -1,
-            PieceColour::BLACK => 1,
-        };
+        self.targets(board)
+            .contains(coordinate_to_index(new_file, new_rank))
+    }
 
-        let move_direction = match new_rank > self.rank {
-            true => 1,
-            false => -1,
-        };
+    fn pseudo_legal_moves(&self, board: &Board) -> impl Iterator<Item = Move> {
+        let from = coordinate_to_index(self.file, self.rank);
+        let rank = self.rank;
+        let occupied = board.occupied();
+        let en_passant_ind = board.en_passant_ind;
 
-        if move_direction != direction {
-            return false;
-        }
+        self.targets(board).flat_map(move |to| {
+            let (_, to_rank) = index_to_coordinate(to);
 
-        // move forward 1
-        if new_file == self.file && new_rank.abs_diff(self.rank) == 1 {
-            let next_rank = (self.rank as i32 + direction) as u8; // can't be out of bounds as pawn can't be on rank 1 or 8
-            if let Some(_) = board.squares[coordinate_to_index(new_file, next_rank)] {
-                return false;
-            } else {
-                return true;
+            let mut flags = Move::QUIET;
+            if occupied.contains(to) {
+                flags |= Move::CAPTURE;
             }
-        };
-
-        // move forward 2
-        if self.colour == PieceColour::WHITE && self.rank == 6
-            || self.colour == PieceColour::BLACK && self.rank == 1
-        {
-            if new_file == self.file && new_rank.abs_diff(self.rank) == 2 {
-                let next_rank = (self.rank as i32 + direction) as u8;
-                let next_next_rank = (self.rank as i32 + 2 * direction) as u8;
-                if let Some(_) = board.squares[coordinate_to_index(new_file, next_rank)] {
-                    return false;
-                } else if let Some(_) = board.squares[coordinate_to_index(new_file, next_next_rank)]
-                // hm odd auto-fmt? Curly in wrong place?
-                {
-                    return false;
-                } else {
-                    return true;
-                }
+            if en_passant_ind == Some(to) {
+                flags |= Move::CAPTURE | Move::EN_PASSANT;
             }
-        }
-
-        // capture
-        if new_file.abs_diff(self.file) == 1 && new_rank.abs_diff(self.rank) == 1 {
-            if let Some(piece) = &board.squares[coordinate_to_index(new_file, new_rank)] {
-                return piece.colour() != self.colour;
+            if to_rank.abs_diff(rank) == 2 {
+                flags |= Move::DOUBLE_PUSH;
             }
 
-            // en passant
-            if let Some(_) = board.en_passant_ind {
-                return true;
-            }
-        };
-
-        false
+            let promotions: &[Option<PieceType>] = if to_rank == 0 || to_rank == 7 {
+                &PROMOTIONS
+            } else {
+                &[None]
+            };
+            promotions
+                .iter()
+                .map(move |&promotion| Move::new(from, to, promotion, flags))
+        })
     }
 
-    fn update_pos(&mut self, new_file: u8, new_rank: u8) {
-        self.file = new_file;
-        self.rank = new_rank;
+    fn attacks(&self, board: &Board) -> SquareSet {
+        bitboard::pawn_attacks(self.colour, coordinate_to_index(self.file, self.rank))
     }
 
     fn colour(&self) -> PieceColour {
@@ -100,26 +82,70 @@ impl Piece for Pawn {
     }
 }
 
+impl Pawn {
+    /// gets the squares the pawn can go to, including en passant
+    fn targets(&self, board: &Board) -> SquareSet {
+        let (direction, start_rank): (i32, u8) = match self.colour {
+            PieceColour::WHITE => (-1, 6),
+            PieceColour::BLACK => (1, 1),
+        };
+
+        let mut enemy = board.colour_pieces(!self.colour);
+        if let Some(ind) = board.en_passant_ind {
+            enemy.insert(ind);
+        }
+        let mut targets = self.attacks(board) & enemy;
+
+        let push_rank = self.rank as i32 + direction;
+        if !(0..8).contains(&push_rank) {
+            return targets;
+        }
+        let push = coordinate_to_index(self.file, push_rank as u8);
+        if !board.occupied().contains(push) {
+            targets.insert(push);
+            if self.rank == start_rank {
+                let double_push =
+                    coordinate_to_index(self.file, (self.rank as i32 + 2 * direction) as u8);
+                if !board.occupied().contains(double_push) {
+                    targets.insert(double_push);
+                }
+            }
+        }
+
+        targets
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::EMPTY_FEN;
     #[test]
     fn test1() {
         let k = Pawn::new(true, 5, 4);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(k.is_pseudo_legal(5, 3, b))
     }
     #[test]
     fn test2() {
         let k = Pawn::new(false, 7, 7);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(!k.is_pseudo_legal(7, 7, b))
     }
     #[test]
     fn test3() {
         let k = Pawn::new(true, 2, 4);
-        let b = &Board::from_fen(EMPTY_FEN).unwrap();
+        let b = &Board::empty();
         assert!(k.is_pseudo_legal(2, 3, b))
     }
+    #[test]
+    fn test4() {
+        let k = Pawn::new(true, 0, 1);
+        let b = &Board::empty();
+        assert_eq!(
+            k.pseudo_legal_moves(b)
+                .filter(|mv| mv.is_promotion())
+                .count(),
+            4
+        )
+    }
 }
@@ -0,0 +1,280 @@
+use std::fmt::Display;
+
+use regex::Regex;
+
+use crate::board::{coordinate_to_index, index_to_name, piece_to_char, Board};
+use crate::moves::Move;
+use crate::{PieceColour, PieceType};
+
+/// why a move in standard algebraic notation could not be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanError {
+    INVALID_SYNTAX,
+    /// no legal move fits the notation
+    ILLEGAL_MOVE,
+    /// more than one legal move fits the notation
+    AMBIGUOUS_MOVE,
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::INVALID_SYNTAX => write!(f, "Cannot parse move: not valid notation"),
+            SanError::ILLEGAL_MOVE => write!(f, "Cannot parse move: no legal move matches"),
+            SanError::AMBIGUOUS_MOVE => {
+                write!(f, "Cannot parse move: more than one legal move matches")
+            }
+        }
+    }
+}
+
+impl From<SanError> for String {
+    fn from(value: SanError) -> Self {
+        value.to_string()
+    }
+}
+
+/// gets the piece type for an uppercase san letter
+fn char_to_piece_type(chr: char) -> Option<PieceType> {
+    match chr {
+        'K' => Some(PieceType::KING),
+        'Q' => Some(PieceType::QUEEN),
+        'R' => Some(PieceType::ROOK),
+        'B' => Some(PieceType::BISHOP),
+        'N' => Some(PieceType::KNIGHT),
+        'P' => Some(PieceType::PAWN),
+        _ => None,
+    }
+}
+
+impl Move {
+    /// writes the move in standard algebraic notation, eg. "Nbd2", "exd5" or "e8=Q+".
+    /// the move must be one of board's legal moves
+    pub fn to_san(self, board: &Board) -> String {
+        let mut san = if self.is_castle() {
+            let (end_file, _) = self.end();
+            if end_file == 6 { "O-O" } else { "O-O-O" }.to_owned()
+        } else {
+            self.san_without_suffix(board)
+        };
+
+        let mut after = *board;
+        after.make(self);
+        if after.is_in_check(after.turn) {
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+
+        san
+    }
+
+    /// writes everything but castling and the check suffix
+    fn san_without_suffix(self, board: &Board) -> String {
+        let piece_type = match board.squares[self.from()] {
+            Some(piece) => piece.piece_type(),
+            None => return self.to_string(),
+        };
+        let (start_file, start_rank) = self.start();
+        let mut san = String::new();
+
+        if piece_type == PieceType::PAWN {
+            // pawns are only named by their file when capturing
+            if self.is_capture() {
+                san.push((b'a' + start_file) as char);
+            }
+        } else {
+            san.push(piece_to_char(PieceColour::WHITE, piece_type));
+
+            // other pieces of the same type that could go to the same square
+            let others: Vec<Move> = board
+                .legal_moves()
+                .into_iter()
+                .filter(|other| {
+                    other.to() == self.to()
+                        && other.from() != self.from()
+                        && board.squares[other.from()].map(|piece| piece.piece_type())
+                            == Some(piece_type)
+                })
+                .collect();
+            if !others.is_empty() {
+                let shares_file = others.iter().any(|other| other.start().0 == start_file);
+                let shares_rank = others.iter().any(|other| other.start().1 == start_rank);
+                if !shares_file {
+                    san.push((b'a' + start_file) as char);
+                } else if !shares_rank {
+                    san.push((b'8' - start_rank) as char);
+                } else {
+                    san.push_str(&index_to_name(self.from()));
+                }
+            }
+        }
+
+        if self.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&index_to_name(self.to()));
+
+        if let Some(promotion) = self.promotion() {
+            san.push('=');
+            san.push(piece_to_char(PieceColour::WHITE, promotion));
+        }
+
+        san
+    }
+}
+
+impl Board {
+    /// finds the legal move written in standard algebraic notation.
+    /// also accepts "0-0" for castling, a missing '=' before promotions, long forms like "Ng1f3"
+    /// or "Ng1-f3", and trailing check marks or annotations like "!?"
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let san = san.trim().trim_end_matches("e.p.").trim_end();
+        let san = san.trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves = self.legal_moves();
+
+        // castling
+        let castle_file = match san {
+            "O-O" | "0-0" => Some(6),
+            "O-O-O" | "0-0-0" => Some(2),
+            _ => None,
+        };
+        if let Some(end_file) = castle_file {
+            return legal_moves
+                .into_iter()
+                .find(|mv| mv.is_castle() && mv.end().0 == end_file)
+                .ok_or(SanError::ILLEGAL_MOVE);
+        }
+
+        let re = Regex::new(
+            r"^(?<piece>[KQRBNP])?(?<file>[a-h])?(?<rank>[1-8])?[x:-]?(?<end>[a-h][1-8])(=?(?<promotion>[QRBN]))?$",
+        )
+        .unwrap();
+        let captures = re.captures(san).ok_or(SanError::INVALID_SYNTAX)?;
+
+        let piece_type = match captures.name("piece") {
+            Some(piece) => char_to_piece_type(piece.as_str().chars().next().unwrap()).unwrap(),
+            None => PieceType::PAWN,
+        };
+        let start_file = captures
+            .name("file")
+            .map(|file| file.as_str().as_bytes()[0] - b'a');
+        let start_rank = captures
+            .name("rank")
+            .map(|rank| b'8' - rank.as_str().as_bytes()[0]);
+        let end = captures["end"].as_bytes();
+        let end_ind = coordinate_to_index(end[0] - b'a', b'8' - end[1]);
+        let promotion = captures
+            .name("promotion")
+            .and_then(|promotion| char_to_piece_type(promotion.as_str().chars().next().unwrap()));
+
+        let mut matching = legal_moves.into_iter().filter(|mv| {
+            let (file, rank) = mv.start();
+            mv.to() == end_ind
+                && self.squares[mv.from()].map(|piece| piece.piece_type()) == Some(piece_type)
+                && start_file.is_none_or(|start_file| start_file == file)
+                && start_rank.is_none_or(|start_rank| start_rank == rank)
+                && mv.promotion() == promotion
+        });
+
+        match (matching.next(), matching.next()) {
+            (Some(mv), None) => Ok(mv),
+            (None, _) => Err(SanError::ILLEGAL_MOVE),
+            (Some(_), Some(_)) => Err(SanError::AMBIGUOUS_MOVE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_FEN;
+
+    /// formats the move between two squares given by name, eg. "e2", "e4"
+    fn san(fen: &str, from: &str, to: &str, promotion: Option<PieceType>) -> String {
+        let board = Board::from_fen(fen).unwrap();
+        let mv = board
+            .legal_moves()
+            .into_iter()
+            .find(|mv| {
+                index_to_name(mv.from()) == from
+                    && index_to_name(mv.to()) == to
+                    && mv.promotion() == promotion
+            })
+            .unwrap();
+        mv.to_san(&board)
+    }
+
+    #[test]
+    fn simple_moves() {
+        assert_eq!(san(STARTING_FEN, "g1", "f3", None), "Nf3");
+        assert_eq!(san(STARTING_FEN, "e2", "e4", None), "e4");
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2";
+        assert_eq!(san(fen, "e4", "d5", None), "exd5")
+    }
+    #[test]
+    fn castling_and_promotion() {
+        let fen = "r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(fen, "e1", "g1", None), "O-O");
+        assert_eq!(san(fen, "e1", "c1", None), "O-O-O");
+        assert_eq!(san(fen, "b7", "a8", Some(PieceType::QUEEN)), "bxa8=Q+");
+        assert_eq!(san(fen, "b7", "b8", Some(PieceType::KNIGHT)), "b8=N")
+    }
+    #[test]
+    fn disambiguation() {
+        // knights on b1 and f3 can both reach d2
+        let fen = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+        assert_eq!(san(fen, "b1", "d2", None), "Nbd2");
+        // rooks on a1 and a5 can both reach a3
+        let fen = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(fen, "a1", "a3", None), "R1a3");
+        // queens on e4, h4 and h1 can all reach e1
+        let fen = "8/2k5/8/8/4Q2Q/8/8/K6Q w - - 0 1";
+        assert_eq!(san(fen, "h4", "e1", None), "Qh4e1")
+    }
+    #[test]
+    fn checkmate() {
+        let fen = "rnbqkbnr/ppppp2p/5p2/6p1/4P3/8/PPPP1PPP/RNBQKBNR w KQkq g6 0 3";
+        assert_eq!(san(fen, "d1", "h5", None), "Qh5#")
+    }
+    #[test]
+    fn lenient_parsing() {
+        let b = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(b.parse_san("0-0").unwrap(), b.parse_san("O-O").unwrap());
+        assert_eq!(
+            b.parse_san("bxa8Q+").unwrap(),
+            b.parse_san("bxa8=Q").unwrap()
+        );
+        assert_eq!(
+            b.parse_san("Ra1-a7!?").unwrap(),
+            b.parse_san("Ra7").unwrap()
+        );
+
+        let b = Board::from_fen(STARTING_FEN).unwrap();
+        assert_eq!(b.parse_san("Ng1f3").unwrap(), b.parse_san("Nf3").unwrap())
+    }
+    #[test]
+    fn parse_errors() {
+        let b = Board::from_fen("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(b.parse_san("Nd2"), Err(SanError::AMBIGUOUS_MOVE));
+        assert_eq!(b.parse_san("Nd3"), Err(SanError::ILLEGAL_MOVE));
+        assert_eq!(b.parse_san("O-O"), Err(SanError::ILLEGAL_MOVE));
+        assert_eq!(b.parse_san("Nz9"), Err(SanError::INVALID_SYNTAX))
+    }
+    #[test]
+    fn round_trip() {
+        for fen in [
+            STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "r3k2r/1P6/8/8/2pP4/8/8/R3K2R b KQkq d3 5 20",
+        ] {
+            let b = Board::from_fen(fen).unwrap();
+            for mv in b.legal_moves() {
+                assert_eq!(b.parse_san(&mv.to_san(&b)), Ok(mv), "{}", mv.to_san(&b));
+            }
+        }
+    }
+}
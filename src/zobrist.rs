@@ -0,0 +1,66 @@
+use crate::board::CastlingRights;
+use crate::{PieceColour, PieceType};
+
+/// seed for the key generator, any value works as long as it never changes
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// splitmix64, returns the next state and its output
+const fn next_random(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+/// one key per (colour, piece type, square), then side to move, castling rights and en passant files
+const KEYS: [u64; 12 * 64 + 1 + 4 + 8] = {
+    let mut keys = [0; 12 * 64 + 1 + 4 + 8];
+    let mut state = SEED;
+    let mut i = 0;
+    while i < keys.len() {
+        let (next_state, key) = next_random(state);
+        state = next_state;
+        keys[i] = key;
+        i += 1;
+    }
+    keys
+};
+
+const SIDE_OFFSET: usize = 12 * 64;
+const CASTLING_OFFSET: usize = SIDE_OFFSET + 1;
+const EN_PASSANT_OFFSET: usize = CASTLING_OFFSET + 4;
+
+/// key for a piece standing on a square
+pub fn piece_key(colour: PieceColour, piece_type: PieceType, index: usize) -> u64 {
+    KEYS[(colour as usize * 6 + piece_type as usize) * 64 + index]
+}
+
+/// key toggled whenever it is black's turn
+pub fn side_key() -> u64 {
+    KEYS[SIDE_OFFSET]
+}
+
+/// combined key for a set of castling rights
+pub fn castling_key(rights: CastlingRights) -> u64 {
+    let mut key = 0;
+    for (i, has_right) in [
+        rights.white_king_side,
+        rights.white_queen_side,
+        rights.black_king_side,
+        rights.black_queen_side,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        if has_right {
+            key ^= KEYS[CASTLING_OFFSET + i];
+        }
+    }
+    key
+}
+
+/// key for an en passant square, only its file is hashed
+pub fn en_passant_key(index: usize) -> u64 {
+    KEYS[EN_PASSANT_OFFSET + index % 8]
+}